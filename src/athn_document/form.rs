@@ -1,8 +1,9 @@
 use email_address::EmailAddress;
+use std::fmt;
 use std::num::NonZeroU32;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum FormField {
     Submit(ID, SubmitField),
     String(ID, StringField),
@@ -46,13 +47,13 @@ impl ID {
     }
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct ConditionalProperty {
     pub inverse: bool, // inverse == true is a conditional not
     pub target: ID,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct GlobalProperties<T> {
     pub optional: bool,
     pub label: Option<String>,
@@ -61,14 +62,14 @@ pub struct GlobalProperties<T> {
 }
 
 // Field type structs
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct SubmitField {
     pub destination: String, // Like with Link this isnt parsed as a URL yet because it can be relative
     pub label: Option<String>,
     pub redirect: bool,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct StringField {
     pub global: GlobalProperties<String>,
     pub min: Option<NonZeroU32>,
@@ -78,7 +79,7 @@ pub struct StringField {
     pub variant: Option<Vec<String>>,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct IntField {
     pub global: GlobalProperties<i64>,
     pub min: Option<i64>,
@@ -87,7 +88,7 @@ pub struct IntField {
     pub positive: bool,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct FloatField {
     pub global: GlobalProperties<f64>,
     pub min: Option<f64>,
@@ -96,19 +97,19 @@ pub struct FloatField {
     pub positive: bool,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct BoolField {
     pub global: GlobalProperties<bool>,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct FileField {
     pub global: GlobalProperties<()>, // The file field cant have a default value
     pub max: Option<NonZeroU32>,
     pub allowed_types: Option<Vec<String>>,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct ListField {
     pub global: GlobalProperties<NonZeroU32>,
     pub min: Option<NonZeroU32>,
@@ -116,7 +117,7 @@ pub struct ListField {
     pub children: Option<Vec<ID>>,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct DateField {
     pub global: GlobalProperties<SystemTime>,
     pub min: Option<SystemTime>,
@@ -125,19 +126,170 @@ pub struct DateField {
     pub date: bool,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateMode {
+    Date,
+    Time,
+    DateTime,
+}
+
+impl DateMode {
+    /// Fields that ask for both or neither of a date and a time get both
+    pub fn of(field: &DateField) -> Self {
+        match (field.date, field.time) {
+            (true, false) => DateMode::Date,
+            (false, true) => DateMode::Time,
+            _ => DateMode::DateTime,
+        }
+    }
+}
+
+/// The value a date field sends. Which parts of a date are picked depends on the mode of the
+/// field. Dates and times on their own are in UTC, the same as the timestamps their defaults and
+/// bounds are written as, so they're picked as they're written instead of in the local timezone.
+/// Every mode is sent as an RFC 3339 timestamp in UTC, like `2023-06-01T12:00:00Z`. Values of the
+/// same mode are ordered by when they are
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum DateValue {
+    /// Sent at midnight, like `2023-06-01T00:00:00Z`
+    Date {
+        year: i32,
+        month: u32,
+        day: u32,
+    },
+    /// Sent on 1970-01-01, like `1970-01-01T12:00:00Z`
+    Time {
+        hour: u32,
+        minute: u32,
+        second: u32,
+    },
+    DateTime(SystemTime),
+}
+
+impl DateValue {
+    /// Defaults and bounds are written as timestamps in the document, date and time only fields
+    /// take the date or time of it in UTC
+    pub fn from_timestamp(time: SystemTime, mode: DateMode) -> Self {
+        let seconds = unix_seconds(time);
+        let second_of_day = seconds.rem_euclid(86400) as u32;
+        match mode {
+            DateMode::DateTime => DateValue::DateTime(time),
+            DateMode::Date => {
+                let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+                DateValue::Date { year, month, day }
+            }
+            DateMode::Time => DateValue::Time {
+                hour: second_of_day / 3600,
+                minute: second_of_day / 60 % 60,
+                second: second_of_day % 60,
+            },
+        }
+    }
+
+    pub fn mode(&self) -> DateMode {
+        match self {
+            DateValue::Date { .. } => DateMode::Date,
+            DateValue::Time { .. } => DateMode::Time,
+            DateValue::DateTime(_) => DateMode::DateTime,
+        }
+    }
+
+    /// The timestamp the value is sent as
+    pub fn timestamp(&self) -> SystemTime {
+        match *self {
+            DateValue::Date { year, month, day } => {
+                from_unix_seconds(days_from_civil(year, month, day) * 86400)
+            }
+            DateValue::Time {
+                hour,
+                minute,
+                second,
+            } => from_unix_seconds((hour * 3600 + minute * 60 + second).into()),
+            DateValue::DateTime(time) => time,
+        }
+    }
+}
+
+/// Seconds since 1970-01-01T00:00:00Z, rounded down for times before it too
+pub fn unix_seconds(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs_f64().ceil() as i64),
+    }
+}
+
+pub fn from_unix_seconds(seconds: i64) -> SystemTime {
+    match seconds {
+        0.. => UNIX_EPOCH + Duration::from_secs(seconds as u64),
+        _ => UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs()),
+    }
+}
+
+/// The year, month and day of a number of days since 1970-01-01 in the proleptic Gregorian
+/// calendar, see https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i32, u32, u32) {
+    // Counted from 0000-03-01 so that the leap day is the last day of the year
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = match shifted_month {
+        0..=9 => shifted_month + 3,
+        _ => shifted_month - 9,
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year as i32, month as u32, day as u32)
+}
+
+/// The number of days since 1970-01-01 of a date in the proleptic Gregorian calendar, the inverse
+/// of civil_from_days, see https://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: i32, month: u32, day: u32) -> i64 {
+    let year = i64::from(year) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let shifted_month = i64::from((month + 9) % 12);
+    let day_of_year = (153 * shifted_month + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// How the value is shown to the user, in the mode of the value
+impl fmt::Display for DateValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateValue::Date { year, month, day } => write!(f, "{year:04}-{month:02}-{day:02}"),
+            DateValue::Time {
+                hour,
+                minute,
+                second,
+            } => write!(f, "{hour:02}:{minute:02}:{second:02}"),
+            DateValue::DateTime(time) => write!(f, "{}", humantime::format_rfc3339_seconds(*time)),
+        }
+    }
+}
+
+impl serde::Serialize for DateValue {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&humantime::format_rfc3339(self.timestamp()))
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
 pub struct EmailField {
     pub global: GlobalProperties<EmailAddress>,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct TelField {
-    // The phone number isnt verified because I couldnt find a standard to verify against
-    // https://crates.io/crates/phonenumber
-    // https://docs.rs/phonenumber/0.3.2+8.13.9/phonenumber/#reexports
+    // The default isnt verified here, the form field checks it with the phonenumber crate since
+    // local numbers depend on the country
     // https://www.twilio.com/docs/glossary/what-e164
-    // https://www.twilio.com/blog/international-phone-number-input-html-javascript
     pub global: GlobalProperties<String>,
+    /// ISO 3166-1 alpha-2 code of the country local numbers are from
     pub country: Option<String>,
 }
 
@@ -348,6 +500,110 @@ impl FormField {
             _ => Err("Form field with invalid type found"),
         }
     }
+
+    pub fn id(&self) -> &ID {
+        use FormField::*;
+        match self {
+            Submit(id, _)
+            | String(id, _)
+            | Integer(id, _)
+            | Float(id, _)
+            | Boolean(id, _)
+            | File(id, _)
+            | List(id, _)
+            | Date(id, _)
+            | Email(id, _)
+            | Phone(id, _) => id,
+        }
+    }
+
+    pub fn conditional(&self) -> Option<&ConditionalProperty> {
+        use FormField::*;
+        match self {
+            Submit(..) => None,
+            String(_, field) => field.global.conditional.as_ref(),
+            Integer(_, field) => field.global.conditional.as_ref(),
+            Float(_, field) => field.global.conditional.as_ref(),
+            Boolean(_, field) => field.global.conditional.as_ref(),
+            File(_, field) => field.global.conditional.as_ref(),
+            List(_, field) => field.global.conditional.as_ref(),
+            Date(_, field) => field.global.conditional.as_ref(),
+            Email(_, field) => field.global.conditional.as_ref(),
+            Phone(_, field) => field.global.conditional.as_ref(),
+        }
+    }
+}
+
+/// Whether a field is shown, given which bool fields of its form are checked. A condition is met
+/// if its target is checked, or isnt for inverse conditions, and the target is shown itself.
+/// Conditions that dont point at a bool field of the form or that depend on themselves are errors,
+/// and so are conditions on the children of lists, since a list has a value for every entry
+pub fn is_shown(
+    field: &FormField,
+    form: &[FormField],
+    checked: &dyn Fn(&ID) -> bool,
+) -> Result<bool, String> {
+    let mut shown = true;
+    let mut visited = vec![field.id()];
+    let mut current = field;
+    while let Some(condition) = current.conditional() {
+        if visited.contains(&&condition.target) {
+            return Err(format!(
+                "The condition of {} depends on itself",
+                field.id().id_cloned()
+            ));
+        }
+        if is_list_child(&condition.target, form) {
+            return Err(format!(
+                "The condition target {} is inside a list",
+                condition.target.id_cloned()
+            ));
+        }
+        current = match form.iter().find(|field| *field.id() == condition.target) {
+            Some(target @ FormField::Boolean(..)) => target,
+            _ => {
+                return Err(format!(
+                    "The condition target {} isnt a bool field in this form",
+                    condition.target.id_cloned()
+                ))
+            }
+        };
+        shown &= checked(&condition.target) != condition.inverse;
+        visited.push(current.id());
+    }
+    Ok(shown)
+}
+
+/// Looks up the children of a list field among the fields of its form. Lists, submit buttons and
+/// conditional fields cant be children
+pub fn list_children(list: &ListField, form: &[FormField]) -> Result<Vec<FormField>, String> {
+    list.children
+        .iter()
+        .flatten()
+        .map(|id| match form.iter().find(|field| field.id() == id) {
+            None => Err(format!(
+                "The child field {} isnt in the same form as the list",
+                id.id_cloned()
+            )),
+            Some(FormField::List(..) | FormField::Submit(..)) => Err(format!(
+                "The child field {} cant be a list or a submit button",
+                id.id_cloned()
+            )),
+            Some(field) if field.conditional().is_some() => Err(format!(
+                "The child field {} cant be conditional",
+                id.id_cloned()
+            )),
+            Some(field) => Ok(field.clone()),
+        })
+        .collect()
+}
+
+/// Children of lists are shown inside the lists instead of on their own
+pub fn is_list_child(id: &ID, form: &[FormField]) -> bool {
+    form.iter().any(|field| match field {
+        FormField::List(_, list) => list.children.iter().flatten().any(|child| child == id),
+        _ => false,
+    })
 }
 
 impl<U> GlobalProperties<U> {
@@ -934,4 +1190,216 @@ mod tests {
     fn create_invalid_id() {
         assert!(ID::new("1nv4lid_ID").is_err());
     }
+
+    fn parse_form(lines: &[&str]) -> Vec<FormField> {
+        lines
+            .iter()
+            .map(|line| FormField::parse(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn find_list_children() {
+        let form = parse_form(&[
+            "people:list \\child name \\child age",
+            "age:int",
+            "name:string",
+            "other:bool",
+        ]);
+        let FormField::List(_, list) = &form[0] else {
+            panic!("Not a list field");
+        };
+
+        let children = list_children(list, &form).unwrap();
+
+        assert_eq!(children, vec![form[2].clone(), form[1].clone()]);
+        assert!(is_list_child(&ID::new("age").unwrap(), &form));
+        assert!(!is_list_child(&ID::new("other").unwrap(), &form));
+    }
+
+    #[test]
+    fn list_child_outside_form() {
+        let form = parse_form(&["people:list \\child name", "age:int"]);
+        let FormField::List(_, list) = &form[0] else {
+            panic!("Not a list field");
+        };
+
+        assert!(list_children(list, &form).is_err());
+    }
+
+    #[test]
+    fn conditional_fields() {
+        let form = parse_form(&[
+            "subscribe:bool",
+            "email:email \\conditional subscribe",
+            "reason:string \\!conditional subscribe",
+        ]);
+        let checked = |id: &ID| id.id_cloned() == "subscribe";
+        let unchecked = |_: &ID| false;
+
+        assert_eq!(is_shown(&form[1], &form, &checked), Ok(true));
+        assert_eq!(is_shown(&form[2], &form, &checked), Ok(false));
+        assert_eq!(is_shown(&form[1], &form, &unchecked), Ok(false));
+        assert_eq!(is_shown(&form[2], &form, &unchecked), Ok(true));
+    }
+
+    #[test]
+    fn conditional_chain() {
+        let form = parse_form(&[
+            "a:bool",
+            "b:bool \\conditional a",
+            "c:string \\conditional b",
+        ]);
+        let only_b = |id: &ID| id.id_cloned() == "b";
+
+        assert_eq!(is_shown(&form[2], &form, &only_b), Ok(false));
+        assert_eq!(is_shown(&form[2], &form, &|_| true), Ok(true));
+    }
+
+    #[test]
+    fn conditional_cycle() {
+        let form = parse_form(&["a:bool \\conditional b", "b:bool \\conditional a"]);
+
+        assert!(is_shown(&form[0], &form, &|_| true).is_err());
+    }
+
+    #[test]
+    fn conditional_target_must_be_bool() {
+        let form = parse_form(&["a:int", "b:string \\conditional a"]);
+
+        assert!(is_shown(&form[1], &form, &|_| true).is_err());
+    }
+
+    #[test]
+    fn conditional_target_cant_be_in_list() {
+        let form = parse_form(&[
+            "people:list \\child adult",
+            "adult:bool",
+            "drink:string \\conditional adult",
+        ]);
+
+        assert!(is_shown(&form[2], &form, &|_| true).is_err());
+    }
+
+    #[test]
+    fn conditional_cant_be_child() {
+        let form = parse_form(&[
+            "people:list \\child name",
+            "named:bool",
+            "name:string \\conditional named",
+        ]);
+        let FormField::List(_, list) = &form[0] else {
+            panic!("Not a list field");
+        };
+
+        assert!(list_children(list, &form).is_err());
+    }
+
+    #[test]
+    fn list_cant_be_child() {
+        let form = parse_form(&["people:list \\child people"]);
+        let FormField::List(_, list) = &form[0] else {
+            panic!("Not a list field");
+        };
+
+        assert!(list_children(list, &form).is_err());
+    }
+
+    fn timestamp(s: &str) -> SystemTime {
+        s.parse::<humantime::Timestamp>().unwrap().into()
+    }
+
+    #[test]
+    fn serialize_every_date_mode() {
+        let time = timestamp("2023-06-01T08:05:09Z");
+
+        let date = DateValue::from_timestamp(time, DateMode::Date);
+        let time_only = DateValue::from_timestamp(time, DateMode::Time);
+        let datetime = DateValue::from_timestamp(time, DateMode::DateTime);
+
+        assert_eq!(
+            serde_json::to_string(&date).unwrap(),
+            "\"2023-06-01T00:00:00Z\""
+        );
+        assert_eq!(
+            serde_json::to_string(&time_only).unwrap(),
+            "\"1970-01-01T08:05:09Z\""
+        );
+        assert_eq!(
+            serde_json::to_string(&datetime).unwrap(),
+            "\"2023-06-01T08:05:09Z\""
+        );
+    }
+
+    #[test]
+    fn date_of_timestamp() {
+        for written in [
+            "1970-01-01T00:00:00Z",
+            "2000-02-29T23:59:59Z",
+            "2024-12-31T12:00:00Z",
+        ] {
+            let time = timestamp(written);
+            let date = DateValue::from_timestamp(time, DateMode::Date);
+            let time_only = DateValue::from_timestamp(time, DateMode::Time);
+
+            assert_eq!(format!("{date}T{time_only}Z"), written);
+        }
+    }
+
+    #[test]
+    fn date_before_1970() {
+        use std::time::Duration;
+        let written = |time| {
+            let date = DateValue::from_timestamp(time, DateMode::Date);
+            let time_only = DateValue::from_timestamp(time, DateMode::Time);
+            format!("{date}T{time_only}Z")
+        };
+
+        assert_eq!(
+            written(UNIX_EPOCH - Duration::from_millis(500)),
+            "1969-12-31T23:59:59Z"
+        );
+        assert_eq!(
+            written(UNIX_EPOCH - Duration::from_secs(25508 * 86400)),
+            "1900-03-01T00:00:00Z"
+        );
+    }
+
+    #[test]
+    fn timestamp_of_date() {
+        // 1900-03-01, 1969-12-31, 1970-01-01 and 2000-02-29
+        for days in [-25508, -1, 0, 11016] {
+            let time = from_unix_seconds(days * 86400);
+            let date = DateValue::from_timestamp(time, DateMode::Date);
+
+            assert_eq!(date.timestamp(), time);
+        }
+    }
+
+    #[test]
+    fn date_order_within_mode() {
+        let early = DateValue::Date {
+            year: 2023,
+            month: 1,
+            day: 31,
+        };
+        let late = DateValue::Date {
+            year: 2023,
+            month: 2,
+            day: 1,
+        };
+
+        assert!(early < late);
+        assert!(
+            DateValue::Time {
+                hour: 9,
+                minute: 0,
+                second: 0
+            } > DateValue::Time {
+                hour: 8,
+                minute: 59,
+                second: 59
+            }
+        );
+    }
 }
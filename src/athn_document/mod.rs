@@ -1,6 +1,7 @@
 pub mod form;
 use form::*;
 pub mod line_types;
+pub mod validation;
 use line_types::*;
 
 #[derive(PartialEq, Debug)]
@@ -0,0 +1,266 @@
+//! Checks entered values against the constraints declared on form fields. The form field widgets
+//! only collect the values, the messages shown under them when a value is invalid come from here
+
+use super::form::*;
+use email_address::EmailAddress;
+
+/// The error is the message shown to the user
+pub type Validity = Result<(), String>;
+
+const REQUIRED: &str = "This field is required";
+
+fn required(optional: bool) -> Validity {
+    match optional {
+        true => Ok(()),
+        false => Err(REQUIRED.to_string()),
+    }
+}
+
+pub fn string(field: &StringField, value: &str) -> Validity {
+    if value.is_empty() {
+        return required(field.global.optional);
+    }
+    let length = value.chars().count() as u32;
+    if let Some(min) = field.min.filter(|min| length < min.get()) {
+        return Err(format!("Must be at least {min} characters long"));
+    }
+    if let Some(max) = field.max.filter(|max| length > max.get()) {
+        return Err(format!("Must be at most {max} characters long"));
+    }
+    if let Some(variants) = &field.variant {
+        if !variants.iter().any(|variant| variant == value) {
+            return Err("Must be one of the options".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Positive fields allow 0
+pub fn int(field: &IntField, value: Option<i64>) -> Validity {
+    let Some(value) = value else {
+        return required(field.global.optional);
+    };
+    if field.positive && value < 0 {
+        return Err("Must not be negative".to_string());
+    }
+    if let Some(min) = field.min.filter(|min| value < *min) {
+        return Err(format!("Must be at least {min}"));
+    }
+    if let Some(max) = field.max.filter(|max| value > *max) {
+        return Err(format!("Must be at most {max}"));
+    }
+    Ok(())
+}
+
+/// Positive fields allow 0
+pub fn float(field: &FloatField, value: Option<f64>) -> Validity {
+    let Some(value) = value else {
+        return required(field.global.optional);
+    };
+    if !value.is_finite() {
+        return Err("Must be a number".to_string());
+    }
+    if field.positive && value < 0. {
+        return Err("Must not be negative".to_string());
+    }
+    if let Some(min) = field.min.filter(|min| value < *min) {
+        return Err(format!("Must be at least {min}"));
+    }
+    if let Some(max) = field.max.filter(|max| value > *max) {
+        return Err(format!("Must be at most {max}"));
+    }
+    Ok(())
+}
+
+/// The bounds are compared in the mode of the value, see [DateValue::from_timestamp]
+pub fn date(field: &DateField, value: Option<DateValue>) -> Validity {
+    let Some(value) = value else {
+        return required(field.global.optional);
+    };
+    let bound = |time| DateValue::from_timestamp(time, value.mode());
+    if let Some(min) = field.min.map(bound).filter(|min| value < *min) {
+        return Err(format!("Must not be before {min}"));
+    }
+    if let Some(max) = field.max.map(bound).filter(|max| value > *max) {
+        return Err(format!("Must not be after {max}"));
+    }
+    Ok(())
+}
+
+pub fn email(field: &EmailField, value: &str) -> Validity {
+    if value.is_empty() {
+        return required(field.global.optional);
+    }
+    match EmailAddress::is_valid(value) {
+        true => Ok(()),
+        false => Err("Not a valid email address".to_string()),
+    }
+}
+
+/// `normalized` is the number in the E.164 format, or None if it couldnt be read as a phone number
+pub fn tel(field: &TelField, value: &str, normalized: Option<&str>) -> Validity {
+    if value.trim().is_empty() {
+        return required(field.global.optional);
+    }
+    match normalized {
+        Some(_) => Ok(()),
+        None => Err("Not a valid phone number".to_string()),
+    }
+}
+
+/// What is known about a file picked in a file field
+pub struct PickedFile<'a> {
+    /// In bytes
+    pub size: u64,
+    pub mime_type: Option<&'a str>,
+}
+
+pub fn file(field: &FileField, file: Option<&PickedFile>) -> Validity {
+    let Some(file) = file else {
+        return required(field.global.optional);
+    };
+    if let Some(max) = field.max.filter(|max| file.size > max.get() as u64) {
+        return Err(format!(
+            "The file is too big, it can be at most {max} bytes"
+        ));
+    }
+    if let Some(allowed_types) = &field.allowed_types {
+        let allowed = file.mime_type.is_some_and(|mime_type| {
+            allowed_types
+                .iter()
+                .any(|pattern| mime_type_matches(pattern, mime_type))
+        });
+        if !allowed {
+            return Err(format!(
+                "The file must be of the type {}",
+                allowed_types.join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The pattern can use * for the subtype like `image/*`, parameters like `; charset=utf-8` are
+/// ignored
+fn mime_type_matches(pattern: &str, mime_type: &str) -> bool {
+    fn essence(s: &str) -> String {
+        s.split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase()
+    }
+    let (pattern, mime_type) = (essence(pattern), essence(mime_type));
+    if pattern == "*" || pattern == "*/*" || pattern == mime_type {
+        return true;
+    }
+    match (pattern.split_once('/'), mime_type.split_once('/')) {
+        (Some((type_, "*")), Some((other_type, _))) => type_ == other_type,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> FormField {
+        FormField::parse(line).unwrap()
+    }
+
+    #[test]
+    fn string_length() {
+        let FormField::String(_, field) = parse("s:string \\min 2 \\max 4") else {
+            panic!("Not a string field");
+        };
+
+        assert!(string(&field, "").is_err());
+        assert!(string(&field, "a").is_err());
+        assert_eq!(string(&field, "abcd"), Ok(()));
+        assert_eq!(string(&field, "æøå"), Ok(()));
+        assert!(string(&field, "abcde").is_err());
+    }
+
+    #[test]
+    fn optional_string() {
+        let FormField::String(_, field) = parse("s:string \\optional \\min 2") else {
+            panic!("Not a string field");
+        };
+
+        assert_eq!(string(&field, ""), Ok(()));
+        assert!(string(&field, "a").is_err());
+    }
+
+    #[test]
+    fn positive_numbers() {
+        let FormField::Integer(_, int_field) = parse("i:int \\positive") else {
+            panic!("Not an int field");
+        };
+        let FormField::Float(_, float_field) = parse("f:float \\positive \\max 1.5") else {
+            panic!("Not a float field");
+        };
+
+        assert_eq!(int(&int_field, Some(0)), Ok(()));
+        assert!(int(&int_field, Some(-1)).is_err());
+        assert!(int(&int_field, None).is_err());
+        assert_eq!(float(&float_field, Some(1.5)), Ok(()));
+        assert!(float(&float_field, Some(-0.1)).is_err());
+        assert!(float(&float_field, Some(1.6)).is_err());
+        assert!(float(&float_field, Some(f64::NAN)).is_err());
+    }
+
+    #[test]
+    fn date_range() {
+        let line = "d:date \\min 2020-01-01T00:00:00Z \\max 2021-01-01T00:00:00Z";
+        let FormField::Date(_, field) = parse(line) else {
+            panic!("Not a date field");
+        };
+        let time = |s: &str| {
+            let time = s.parse::<humantime::Timestamp>().unwrap().into();
+            Some(DateValue::DateTime(time))
+        };
+
+        assert_eq!(date(&field, time("2020-06-01T12:00:00Z")), Ok(()));
+        assert!(date(&field, time("2019-12-31T23:59:59Z")).is_err());
+        assert!(date(&field, time("2021-01-01T00:00:01Z")).is_err());
+    }
+
+    #[test]
+    fn time_range() {
+        let line = "d:date \\time \\min 2020-01-01T09:00:00Z \\max 2020-01-01T17:00:00Z";
+        let FormField::Date(_, field) = parse(line) else {
+            panic!("Not a date field");
+        };
+        let time = |hour, minute| {
+            Some(DateValue::Time {
+                hour,
+                minute,
+                second: 0,
+            })
+        };
+
+        assert_eq!(date(&field, time(9, 0)), Ok(()));
+        assert_eq!(date(&field, time(17, 0)), Ok(()));
+        assert!(date(&field, time(8, 59)).is_err());
+        assert!(date(&field, time(17, 1)).is_err());
+    }
+
+    #[test]
+    fn file_constraints() {
+        let FormField::File(_, field) = parse("f:file \\max 1000 \\type image/* \\type text/plain")
+        else {
+            panic!("Not a file field");
+        };
+        let picked = |size, mime_type| PickedFile { size, mime_type };
+
+        assert_eq!(file(&field, Some(&picked(1000, Some("image/png")))), Ok(()));
+        assert_eq!(
+            file(&field, Some(&picked(10, Some("text/plain; charset=utf-8")))),
+            Ok(())
+        );
+        assert!(file(&field, Some(&picked(1001, Some("image/png")))).is_err());
+        assert!(file(&field, Some(&picked(10, Some("application/pdf")))).is_err());
+        assert!(file(&field, Some(&picked(10, None))).is_err());
+        assert!(file(&field, None).is_err());
+    }
+}
@@ -0,0 +1,92 @@
+use crate::form_field::{FormField, FormFieldExt, FormFieldImpl};
+use crate::window::input::InputTypes;
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use glib::subclass::InitializingObject;
+use gtk::{glib, Button, CheckButton, CompositeTemplate};
+use std::cell::Cell;
+
+#[derive(Default, CompositeTemplate)]
+#[template(resource = "/online/athn/browser/gnome/bool_form_field.ui")]
+pub struct BoolFormField {
+    #[template_child]
+    pub entry: TemplateChild<CheckButton>,
+    #[template_child]
+    pub reset_button: TemplateChild<Button>,
+
+    /// Set while resetting, so unchecking the check button isnt reported as a value
+    resetting: Cell<bool>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for BoolFormField {
+    const NAME: &'static str = "AthnBoolFormField";
+    type Type = super::BoolFormField;
+    type ParentType = FormField;
+
+    fn class_init(klass: &mut Self::Class) {
+        klass.bind_template();
+        klass.bind_template_callbacks();
+    }
+
+    fn instance_init(obj: &InitializingObject<Self>) {
+        obj.init_template();
+    }
+}
+
+#[gtk::template_callbacks]
+impl BoolFormField {
+    #[template_callback]
+    fn on_entry_toggled(&self, entry: &CheckButton) {
+        if self.resetting.get() {
+            return;
+        }
+        entry.set_inconsistent(false);
+        self.reset_button.set_sensitive(true);
+        self.obj()
+            .update_value(InputTypes::Bool(Some(entry.is_active())), true);
+    }
+
+    #[template_callback]
+    fn on_reset_button_clicked(&self, _button: &Button) {
+        self.reset();
+    }
+
+    /// Makes the field undecided again, which is sent as null
+    pub fn reset(&self) {
+        self.resetting.set(true);
+        self.entry.set_active(false);
+        self.resetting.set(false);
+        self.entry.set_inconsistent(true);
+        self.reset_button.set_sensitive(false);
+        self.obj().update_value(InputTypes::Bool(None), true);
+    }
+}
+
+impl ObjectImpl for BoolFormField {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.bind_property::<CheckButton>("label", self.entry.as_ref(), "label")
+            .sync_create()
+            .build();
+    }
+}
+impl WidgetImpl for BoolFormField {}
+impl BoxImpl for BoolFormField {}
+impl FormFieldImpl for BoolFormField {
+    fn set_input(&self, value: &InputTypes) {
+        match value {
+            InputTypes::Bool(Some(value)) => {
+                self.entry.set_active(*value);
+                // Toggled isnt emitted if the undecided button was already inactive
+                if self.entry.is_inconsistent() {
+                    self.on_entry_toggled(&self.entry);
+                }
+            }
+            InputTypes::Bool(None) if self.obj().form_field().optional() => self.reset(),
+            _ => {}
+        }
+    }
+}
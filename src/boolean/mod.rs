@@ -0,0 +1,45 @@
+mod imp;
+
+use crate::athn_document::form;
+use crate::form_field::{FormField, FormFieldExt};
+use crate::window::input::InputTypes;
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use glib::Object;
+use gtk::glib;
+
+glib::wrapper! {
+    pub struct BoolFormField(ObjectSubclass<imp::BoolFormField>)
+        @extends FormField, gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Orientable, gtk::Buildable, gtk::ConstraintTarget;
+}
+
+impl BoolFormField {
+    pub fn new(form_idx: usize, id: form::ID, field: form::BoolField) -> Self {
+        let label = field.global.label.unwrap_or(id.id_cloned());
+        let optional = field.global.optional;
+        let default = match (field.global.default, optional) {
+            (None, true) => None,
+            (None, false) => Some(false),
+            (Some(v), _) => Some(v),
+        };
+
+        let widget: Self = Object::builder()
+            .property("form-idx", form_idx as u64)
+            .property("id", id.id())
+            .property("label", label)
+            .property("optional", optional)
+            .build();
+
+        // Only optional fields can go back to undecided
+        widget.imp().reset_button.set_visible(optional);
+        match default {
+            Some(default) => widget.imp().entry.set_active(default),
+            None => widget.imp().entry.set_inconsistent(true),
+        }
+        widget.imp().reset_button.set_sensitive(default.is_some());
+        widget.update_value(InputTypes::Bool(default), true);
+
+        widget
+    }
+}
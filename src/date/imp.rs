@@ -1,39 +1,42 @@
+use crate::athn_document::form::{self, DateMode, DateValue};
+use crate::athn_document::validation;
+use crate::form_field::{declaration, FormField, FormFieldExt, FormFieldImpl};
+use crate::window::input::InputTypes;
 use adw::prelude::*;
 use adw::subclass::prelude::*;
 use glib::subclass::InitializingObject;
-use glib::subclass::Signal;
 use glib::DateTime;
-use glib::{ParamSpec, Properties, Value};
-use gtk::{glib, Calendar, CompositeTemplate, SpinButton, Label};
-use once_cell::sync::Lazy;
-use std::cell::{Cell, RefCell};
+use gtk::{glib, Button, Calendar, CompositeTemplate, Label, SpinButton};
+use std::cell::OnceCell;
 
-#[derive(Default, CompositeTemplate, Properties)]
+#[derive(Default, CompositeTemplate)]
 #[template(resource = "/online/athn/browser/gnome/date_form_field.ui")]
-#[properties(wrapper_type = super::DateFormField)]
 pub struct DateFormField {
     #[template_child]
     pub calendar: TemplateChild<Calendar>,
     #[template_child]
+    pub time_box: TemplateChild<gtk::Box>,
+    #[template_child]
     pub hour: TemplateChild<SpinButton>,
     #[template_child]
     pub minute: TemplateChild<SpinButton>,
     #[template_child]
+    pub utc_label: TemplateChild<Label>,
+    #[template_child]
+    pub picker: TemplateChild<gtk::Box>,
+    #[template_child]
+    pub clear_button: TemplateChild<Button>,
+    #[template_child]
     pub label_widget: TemplateChild<Label>,
 
-    #[property(get, set)]
-    form_idx: Cell<u64>,
-    #[property(get, set)]
-    id: RefCell<String>,
-    #[property(get, set)]
-    label: RefCell<String>,
+    pub field: OnceCell<form::DateField>,
 }
 
 #[glib::object_subclass]
 impl ObjectSubclass for DateFormField {
     const NAME: &'static str = "AthnDateFormField";
     type Type = super::DateFormField;
-    type ParentType = gtk::Box;
+    type ParentType = FormField;
 
     fn class_init(klass: &mut Self::Class) {
         klass.bind_template();
@@ -62,10 +65,11 @@ impl DateFormField {
 
     #[template_callback]
     fn on_day_selected(&self, _calendar: &Calendar) {
-        if let Ok(time) = self.get_time() {
-            self.obj()
-                .emit_by_name::<()>("updated", &[&self.obj().form_idx(), &self.obj().id(), &time]);
+        if self.clamp_day() {
+            // Selecting the bound reports the value
+            return;
         }
+        self.report_value();
     }
 
     #[template_callback]
@@ -78,47 +82,131 @@ impl DateFormField {
             self.minute.set_wrap(true);
         }
 
-        if let Ok(time) = self.get_time() {
-            self.obj()
-                .emit_by_name::<()>("updated", &[&self.obj().form_idx(), &self.obj().id(), &time]);
-        }
+        self.report_value();
+    }
+
+    #[template_callback]
+    fn on_clear_button_clicked(&self, _button: &Button) {
+        self.clear();
     }
 
-    /// This function returns the time in UTC
-    fn get_time(&self) -> Result<DateTime, glib::BoolError> {
-        let date = DateTime::from_local(
-            self.calendar.year(),
-            self.calendar.month() + 1,
-            self.calendar.day(),
-            self.hour.value_as_int(),
-            self.minute.value_as_int(),
-            0.,
-        )?;
-        date.to_utc()
-    }
-
-    pub fn set_datetime(&self, datetime: DateTime) {
-        if let Ok(datetime) = datetime.to_local() {
-            self.calendar.select_day(&datetime);
-            self.hour.set_value(datetime.hour().into());
-            self.minute.set_value(datetime.minute().into());
+    fn report_value(&self) {
+        let value = self.shown_value();
+        self.show_cleared(false);
+        let validity = validation::date(declaration(&self.field), value);
+        self.obj()
+            .update_validated_value(InputTypes::Date(value), validity);
+    }
+
+    /// Leaves the date out, which is sent as null. Picking a date or time sets it again
+    pub fn clear(&self) {
+        self.show_cleared(true);
+        let validity = validation::date(declaration(&self.field), None);
+        self.obj()
+            .update_validated_value(InputTypes::Date(None), validity);
+    }
+
+    /// Dims the calendar and spinners while they dont hold the value of the field
+    pub fn show_cleared(&self, cleared: bool) {
+        match cleared {
+            true => self.picker.add_css_class("dim-label"),
+            false => self.picker.remove_css_class("dim-label"),
         }
+        self.clear_button.set_sensitive(!cleared);
     }
-}
 
-impl ObjectImpl for DateFormField {
-    fn properties() -> &'static [ParamSpec] {
-        Self::derived_properties()
+    pub fn mode(&self) -> DateMode {
+        DateMode::of(declaration(&self.field))
     }
 
-    fn set_property(&self, id: usize, value: &Value, pspec: &ParamSpec) {
-        self.derived_set_property(id, value, pspec)
+    /// Moves the calendar to the min or max day of the field if a day outside of them is
+    /// selected. Returns whether it was moved
+    pub fn clamp_day(&self) -> bool {
+        let field = declaration(&self.field);
+        let bound = |time| match self.mode() {
+            DateMode::Time => None,
+            DateMode::Date => Some(DateValue::from_timestamp(time, DateMode::Date)),
+            // The calendar of a datetime shows the local day
+            DateMode::DateTime => {
+                let datetime = DateTime::from_unix_local(form::unix_seconds(time)).ok()?;
+                Some(DateValue::Date {
+                    year: datetime.year(),
+                    month: datetime.month() as u32,
+                    day: datetime.day_of_month() as u32,
+                })
+            }
+        };
+        let selected = self.selected_day();
+        let clamped = match (field.min.and_then(bound), field.max.and_then(bound)) {
+            (Some(min), _) if selected < min => min,
+            (_, Some(max)) if selected > max => max,
+            _ => return false,
+        };
+        self.show_value(&clamped);
+        true
     }
 
-    fn property(&self, id: usize, pspec: &ParamSpec) -> Value {
-        self.derived_property(id, pspec)
+    fn selected_day(&self) -> DateValue {
+        DateValue::Date {
+            year: self.calendar.year(),
+            month: self.calendar.month() as u32 + 1,
+            day: self.calendar.day() as u32,
+        }
     }
 
+    /// The value picked in the calendar and spinners, in the mode of the field. Times are picked
+    /// to the minute. Datetimes are picked in the local timezone, dates and times on their own as
+    /// they're sent
+    pub fn shown_value(&self) -> Option<DateValue> {
+        match self.mode() {
+            DateMode::Date => Some(self.selected_day()),
+            DateMode::Time => Some(DateValue::Time {
+                hour: self.hour.value_as_int() as u32,
+                minute: self.minute.value_as_int() as u32,
+                second: 0,
+            }),
+            DateMode::DateTime => {
+                let datetime = DateTime::from_local(
+                    self.calendar.year(),
+                    self.calendar.month() + 1,
+                    self.calendar.day(),
+                    self.hour.value_as_int(),
+                    self.minute.value_as_int(),
+                    0.,
+                )
+                .ok()?;
+                Some(DateValue::DateTime(form::from_unix_seconds(
+                    datetime.to_unix(),
+                )))
+            }
+        }
+    }
+
+    /// Shows a value in the calendar and spinners. Datetimes are shown in the local timezone
+    pub fn show_value(&self, value: &DateValue) {
+        match *value {
+            DateValue::Date { year, month, day } => {
+                // Noon so the day doesnt change if the timezone is moved
+                if let Ok(date) = DateTime::from_local(year, month as i32, day as i32, 12, 0, 0.) {
+                    self.calendar.select_day(&date);
+                }
+            }
+            DateValue::Time { hour, minute, .. } => {
+                self.hour.set_value(hour.into());
+                self.minute.set_value(minute.into());
+            }
+            DateValue::DateTime(time) => {
+                if let Ok(datetime) = DateTime::from_unix_local(form::unix_seconds(time)) {
+                    self.calendar.select_day(&datetime);
+                    self.hour.set_value(datetime.hour().into());
+                    self.minute.set_value(datetime.minute().into());
+                }
+            }
+        }
+    }
+}
+
+impl ObjectImpl for DateFormField {
     fn constructed(&self) {
         self.parent_constructed();
 
@@ -127,15 +215,20 @@ impl ObjectImpl for DateFormField {
             .sync_create()
             .build();
     }
-
-    fn signals() -> &'static [Signal] {
-        static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
-            vec![Signal::builder("updated")
-                .param_types([u64::static_type(), String::static_type(), DateTime::static_type()])
-                .build()]
-        });
-        SIGNALS.as_ref()
-    }
 }
 impl WidgetImpl for DateFormField {}
 impl BoxImpl for DateFormField {}
+impl FormFieldImpl for DateFormField {
+    fn set_input(&self, value: &InputTypes) {
+        match value {
+            // Values saved before the mode of the field changed cant be shown
+            InputTypes::Date(Some(value)) if value.mode() == self.mode() => {
+                self.show_value(value);
+                // Nothing is reported if the value was already shown
+                self.report_value();
+            }
+            InputTypes::Date(None) if self.obj().form_field().optional() => self.clear(),
+            _ => {}
+        }
+    }
+}
@@ -1,52 +1,56 @@
 mod imp;
 
-use crate::athn_document::form;
+use crate::athn_document::form::{DateMode, DateValue};
+use crate::athn_document::{form, validation};
+use crate::form_field::{FormField, FormFieldExt};
+use crate::window::input::InputTypes;
 use adw::prelude::*;
 use adw::subclass::prelude::*;
 use glib::Object;
 use gtk::glib;
-use std::time::SystemTime;
 
 glib::wrapper! {
     pub struct DateFormField(ObjectSubclass<imp::DateFormField>)
-        @extends gtk::Box, gtk::Widget,
+        @extends FormField, gtk::Box, gtk::Widget,
         @implements gtk::Accessible, gtk::Orientable, gtk::Buildable, gtk::ConstraintTarget;
 }
 
 impl DateFormField {
     pub fn new(form_idx: usize, id: form::ID, field: form::DateField) -> Self {
-        let label = field.global.label.unwrap_or(id.id_cloned());
+        let label = field.global.label.clone().unwrap_or(id.id_cloned());
 
         let widget: Self = Object::builder()
             .property("form-idx", form_idx as u64)
             .property("id", id.id())
             .property("label", label)
+            .property("optional", field.global.optional)
             .build();
-
-        if field.time {
-            widget.imp().calendar.set_visible(false);
-            widget.imp().hour.set_visible(true);
-            widget.imp().minute.set_visible(true);
-        }
-        if field.date {
-            widget.imp().calendar.set_visible(true);
-            widget.imp().hour.set_visible(false);
-            widget.imp().minute.set_visible(false);
-        }
-
-        if let Some(default) = field.global.default {
-            if let Ok(default) = convert_default(default) {
-                widget.imp().set_datetime(default);
-            }
+        let _ = widget.imp().field.set(field.clone());
+
+        let mode = DateMode::of(&field);
+        widget.imp().calendar.set_visible(mode != DateMode::Time);
+        widget.imp().time_box.set_visible(mode != DateMode::Date);
+        widget.imp().utc_label.set_visible(mode == DateMode::Time);
+        // Only optional fields can be left without a date
+        widget.imp().clear_button.set_visible(field.global.optional);
+
+        let default = field
+            .global
+            .default
+            .map(|default| DateValue::from_timestamp(default, mode));
+        if let Some(default) = &default {
+            widget.imp().show_value(default);
         }
+        widget.imp().clamp_day();
+        // Required fields start out with the value that is shown
+        let value = match field.global.optional {
+            true => default,
+            false => default.or(widget.imp().shown_value()),
+        };
+        widget.imp().show_cleared(value.is_none());
+        let validity = validation::date(&field, value);
+        widget.report_initial_value(InputTypes::Date(value), validity);
 
         widget
     }
 }
-
-fn convert_default(default: SystemTime) -> Result<glib::DateTime, glib::BoolError> {
-    let default = default
-        .duration_since(std::time::SystemTime::UNIX_EPOCH)
-        .unwrap_or_default();
-    glib::DateTime::from_unix_utc(default.as_secs() as i64)
-}
@@ -1,6 +1,8 @@
 mod imp;
 
-use crate::athn_document::form;
+use crate::athn_document::{form, validation};
+use crate::form_field::{FormField, FormFieldExt};
+use crate::window::input::InputTypes;
 use adw::prelude::*;
 use adw::subclass::prelude::*;
 use glib::Object;
@@ -8,13 +10,13 @@ use gtk::glib;
 
 glib::wrapper! {
     pub struct EmailFormField(ObjectSubclass<imp::EmailFormField>)
-        @extends gtk::Box, gtk::Widget,
+        @extends FormField, gtk::Box, gtk::Widget,
         @implements gtk::Accessible, gtk::Orientable, gtk::Buildable, gtk::ConstraintTarget;
 }
 
 impl EmailFormField {
     pub fn new(form_idx: usize, id: form::ID, field: form::EmailField) -> Self {
-        let label = field.global.label.unwrap_or(id.id_cloned());
+        let label = field.global.label.clone().unwrap_or(id.id_cloned());
         let optional = field.global.optional;
 
         let widget: Self = Object::builder()
@@ -23,12 +25,15 @@ impl EmailFormField {
             .property("label", label)
             .property("optional", optional)
             .build();
+        let _ = widget.imp().field.set(field.clone());
 
-        if let Some(default) = field.global.default {
+        let default = field.global.default.clone();
+        if let Some(default) = &default {
             widget.imp().entry.set_text(default.as_str());
-        } else {
-            widget.set_valid(optional);
         }
+        let text = default.as_ref().map_or("", |default| default.as_str());
+        let validity = validation::email(&field, text);
+        widget.report_initial_value(InputTypes::Email(default), validity);
 
         widget
     }
@@ -1,16 +1,15 @@
 use super::extract_string_from_object;
+use crate::athn_document::{form, validation};
+use crate::form_field::{declaration, FormField, FormFieldExt, FormFieldImpl};
+use crate::window::input::InputTypes;
 use adw::prelude::*;
 use adw::subclass::prelude::*;
 use glib::subclass::InitializingObject;
-use glib::subclass::Signal;
-use glib::{ParamSpec, Properties, Value};
 use gtk::{glib, CompositeTemplate, DropDown, Label, StringList};
-use once_cell::sync::Lazy;
-use std::cell::{Cell, RefCell};
+use std::cell::OnceCell;
 
-#[derive(Default, CompositeTemplate, Properties)]
+#[derive(Default, CompositeTemplate)]
 #[template(resource = "/online/athn/browser/gnome/enum_form_field.ui")]
-#[properties(wrapper_type = super::EnumFormField)]
 pub struct EnumFormField {
     #[template_child]
     pub entry: TemplateChild<DropDown>,
@@ -19,21 +18,14 @@ pub struct EnumFormField {
     #[template_child]
     pub model: TemplateChild<StringList>,
 
-    #[property(get, set)]
-    form_idx: Cell<u64>,
-    #[property(get, set)]
-    id: RefCell<String>,
-    #[property(get, set)]
-    label: RefCell<String>,
-    #[property(get, set)]
-    optional: Cell<bool>,
+    pub field: OnceCell<form::StringField>,
 }
 
 #[glib::object_subclass]
 impl ObjectSubclass for EnumFormField {
     const NAME: &'static str = "AthnEnumFormField";
     type Type = super::EnumFormField;
-    type ParentType = gtk::Box;
+    type ParentType = FormField;
 
     fn class_init(klass: &mut Self::Class) {
         klass.bind_template();
@@ -51,9 +43,11 @@ impl EnumFormField {
     fn on_entry_changed(&self, _pspec: &glib::ParamSpec, entry: &DropDown) {
         let selected_item = entry.selected_item();
         let selected_item = selected_item.map(|item| extract_string_from_object(&item));
-        let obj = self.obj();
 
-        obj.emit_by_name::<()>("updated", &[&obj.form_idx(), &obj.id(), &selected_item, &true]);
+        let field = declaration(&self.field);
+        let validity = validation::string(field, selected_item.as_deref().unwrap_or_default());
+        self.obj()
+            .update_validated_value(InputTypes::String(selected_item.clone()), validity);
         if selected_item.is_some_and(|e| e.is_empty()) {
             entry.set_selected(u32::MAX);
         }
@@ -69,25 +63,13 @@ impl EnumFormField {
             .find(|e| extract_string_from_object(e.1) == item.clone().unwrap_or_default())
             .map(|r| r.0 as u32);
 
-        let optional = self.obj().optional();
+        let optional = self.obj().form_field().optional();
         self.entry
             .set_selected(item_idx.unwrap_or(if optional { u32::MAX } else { 0 }));
     }
 }
 
 impl ObjectImpl for EnumFormField {
-    fn properties() -> &'static [ParamSpec] {
-        Self::derived_properties()
-    }
-
-    fn set_property(&self, id: usize, value: &Value, pspec: &ParamSpec) {
-        self.derived_set_property(id, value, pspec)
-    }
-
-    fn property(&self, id: usize, pspec: &ParamSpec) -> Value {
-        self.derived_property(id, pspec)
-    }
-
     fn constructed(&self) {
         self.parent_constructed();
 
@@ -96,20 +78,13 @@ impl ObjectImpl for EnumFormField {
             .sync_create()
             .build();
     }
-
-    fn signals() -> &'static [Signal] {
-        static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
-            vec![Signal::builder("updated")
-                .param_types([
-                    u64::static_type(),
-                    String::static_type(),
-                    Option::<String>::static_type(),
-                    bool::static_type(),
-                ])
-                .build()]
-        });
-        SIGNALS.as_ref()
-    }
 }
 impl WidgetImpl for EnumFormField {}
 impl BoxImpl for EnumFormField {}
+impl FormFieldImpl for EnumFormField {
+    fn set_input(&self, value: &InputTypes) {
+        if let InputTypes::String(value) = value {
+            self.select_item(value.clone());
+        }
+    }
+}
@@ -1,6 +1,8 @@
 mod imp;
 
-use crate::athn_document::form;
+use crate::athn_document::{form, validation};
+use crate::form_field::{FormField, FormFieldExt};
+use crate::window::input::InputTypes;
 use adw::prelude::*;
 use adw::subclass::prelude::*;
 use glib::Object;
@@ -8,13 +10,13 @@ use gtk::{glib, StringObject};
 
 glib::wrapper! {
     pub struct EnumFormField(ObjectSubclass<imp::EnumFormField>)
-        @extends gtk::Box, gtk::Widget,
+        @extends FormField, gtk::Box, gtk::Widget,
         @implements gtk::Accessible, gtk::Orientable, gtk::Buildable, gtk::ConstraintTarget;
 }
 
 impl EnumFormField {
     pub fn new(form_idx: usize, id: form::ID, field: form::StringField) -> Self {
-        let label = field.global.label.unwrap_or(id.id_cloned());
+        let label = field.global.label.clone().unwrap_or(id.id_cloned());
         let optional = field.global.optional;
 
         let widget: Self = Object::builder()
@@ -23,9 +25,10 @@ impl EnumFormField {
             .property("label", label)
             .property("optional", optional)
             .build();
+        let _ = widget.imp().field.set(field.clone());
 
         let string_list = &widget.imp().model;
-        let variants = field.variant.unwrap_or_default();
+        let variants = field.variant.clone().unwrap_or_default();
         let many_options = variants.len() >= 5;
         if optional {
             string_list.append("");
@@ -37,7 +40,11 @@ impl EnumFormField {
         }
 
         widget.imp().entry.set_enable_search(many_options);
-        widget.imp().select_item(field.global.default);
+        widget.imp().select_item(field.global.default.clone());
+        let selected_item = widget.imp().entry.selected_item();
+        let default = selected_item.map(|item| extract_string_from_object(&item));
+        let validity = validation::string(&field, default.as_deref().unwrap_or_default());
+        widget.report_initial_value(InputTypes::String(default), validity);
 
         widget
     }
@@ -1,41 +1,37 @@
+use crate::athn_document::{form, validation};
+use crate::form_field::{declaration, FormField, FormFieldExt, FormFieldImpl};
+use crate::window::input::{FileInput, InputTypes};
 use adw::prelude::*;
 use adw::subclass::prelude::*;
-use glib::subclass::InitializingObject;
-use glib::subclass::Signal;
-use glib::{clone, ParamSpec, Properties, Value};
-use gtk::gio::File;
-use gtk::{glib, CompositeTemplate, FileDialog};
 use adw::ButtonContent;
-use once_cell::sync::Lazy;
-use std::cell::{Cell, RefCell};
+use glib::subclass::InitializingObject;
+use glib::{clone, source::PRIORITY_DEFAULT};
+use gtk::gio::{self, File, FileQueryInfoFlags};
+use gtk::{glib, CompositeTemplate, FileDialog, Label};
+use std::cell::OnceCell;
 
-#[derive(Default, CompositeTemplate, Properties)]
+#[derive(Default, CompositeTemplate)]
 #[template(resource = "/online/athn/browser/gnome/file_form_field.ui")]
-#[properties(wrapper_type = super::FileFormField)]
 pub struct FileFormField {
     #[template_child]
     pub picker: TemplateChild<FileDialog>,
     #[template_child]
     pub label_widget: TemplateChild<ButtonContent>,
+    #[template_child]
+    pub file_label: TemplateChild<Label>,
 
-    #[property(get, set)]
-    form_idx: Cell<u64>,
-    #[property(get, set)]
-    id: RefCell<String>,
-    #[property(get, set = Self::valid_setter)]
-    valid: Cell<bool>,
-    #[property(get, set)]
-    max_file_size: Cell<u32>,
+    pub field: OnceCell<form::FileField>,
 }
 
 #[glib::object_subclass]
 impl ObjectSubclass for FileFormField {
     const NAME: &'static str = "AthnFileFormField";
     type Type = super::FileFormField;
-    type ParentType = gtk::Button;
+    type ParentType = FormField;
 
     fn class_init(klass: &mut Self::Class) {
         klass.bind_template();
+        klass.bind_template_callbacks();
     }
 
     fn instance_init(obj: &InitializingObject<Self>) {
@@ -43,66 +39,85 @@ impl ObjectSubclass for FileFormField {
     }
 }
 
+#[gtk::template_callbacks]
 impl FileFormField {
-    fn valid_setter(&self, valid: bool) {
-        if valid {
-            self.obj().remove_css_class("error");
-        } else {
-            self.obj().add_css_class("error");
-        }
-        self.valid.set(valid);
+    #[template_callback]
+    fn on_button_clicked(&self, _button: &gtk::Button) {
+        let ctx = glib::MainContext::default();
+        ctx.spawn_local(clone!(@weak self as field => async move {
+            // Cancelling the dialog keeps the file that was picked before
+            let Ok(file) = field.picker.open_future(None::<&gtk::Window>).await else {
+                return;
+            };
+            match file_input(&file).await {
+                Ok(input) => field.report_file(Some(input)),
+                Err(message) => {
+                    field.file_label.set_visible(false);
+                    let value = InputTypes::File(None);
+                    field.obj().update_validated_value(value, Err(message));
+                }
+            }
+        }));
     }
 }
 
-impl ObjectImpl for FileFormField {
-    fn properties() -> &'static [ParamSpec] {
-        Self::derived_properties()
-    }
-
-    fn set_property(&self, id: usize, value: &Value, pspec: &ParamSpec) {
-        self.derived_set_property(id, value, pspec)
-    }
+impl FileFormField {
+    fn report_file(&self, file: Option<FileInput>) {
+        let name = file.as_ref().map_or("", |file| file.name.as_str());
+        self.file_label.set_label(name);
+        self.file_label.set_visible(file.is_some());
 
-    fn property(&self, id: usize, pspec: &ParamSpec) -> Value {
-        self.derived_property(id, pspec)
+        let picked = file.as_ref().map(|file| validation::PickedFile {
+            size: file.size,
+            mime_type: Some(&file.mime_type),
+        });
+        let validity = validation::file(declaration(&self.field), picked.as_ref());
+        self.obj()
+            .update_validated_value(InputTypes::File(file), validity);
     }
+}
 
+impl ObjectImpl for FileFormField {
     fn constructed(&self) {
         self.parent_constructed();
-    }
 
-    fn signals() -> &'static [Signal] {
-        static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
-            vec![
-                Signal::builder("updated")
-                    .param_types([
-                        u64::static_type(),
-                        String::static_type(),
-                        File::static_type(),
-                        bool::static_type(),
-                    ])
-                    .build(),
-                Signal::builder("too-big-file-selected").build(),
-            ]
-        });
-        SIGNALS.as_ref()
+        let obj = self.obj();
+        obj.bind_property::<ButtonContent>("label", self.label_widget.as_ref(), "label")
+            .sync_create()
+            .build();
     }
 }
 impl WidgetImpl for FileFormField {}
-impl ButtonImpl for FileFormField {
-    fn clicked(&self) {
-        let ctx = glib::MainContext::default();
-        ctx.spawn_local(clone!(@weak self as button => async move {
-            if let Ok(file) = button.picker.open_future(None::<&gtk::Window>).await {
-                if let Ok(size) = file.query_info("standard::size", gtk::gio::FileQueryInfoFlags::NONE, None::<&gtk::gio::Cancellable>) {
-                    let size = size.size();
-                    if size > button.obj().max_file_size() as i64 && button.obj().max_file_size() != 0 {
-                        return button.obj().emit_by_name::<()>("too-big-file-selected", &[]);
-                    }
-                };
-                button.obj().emit_by_name::<()>("updated", &[&button.obj().form_idx(), &button.obj().id(), &file, &true]);
-                button.obj().set_valid(true);
-            }
-        }));
+impl BoxImpl for FileFormField {}
+impl FormFieldImpl for FileFormField {
+    fn set_input(&self, value: &InputTypes) {
+        if let InputTypes::File(file) = value {
+            self.report_file(file.clone());
+        }
     }
 }
+
+/// Finds out what is needed to send the file later, the file itself is only read when the form
+/// is submitted
+async fn file_input(file: &File) -> Result<FileInput, String> {
+    let path = file.path().ok_or("Only local files can be picked")?;
+    let attributes = "standard::display-name,standard::size,standard::content-type";
+    let info = file
+        .query_info_future(attributes, FileQueryInfoFlags::NONE, PRIORITY_DEFAULT)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mime_type = info
+        .content_type()
+        .and_then(|content_type| gio::content_type_get_mime_type(&content_type))
+        .map_or("application/octet-stream".to_string(), |mime_type| {
+            mime_type.to_string()
+        });
+    Ok(FileInput {
+        name: info.display_name().to_string(),
+        mime_type,
+        size: info.size() as u64,
+        path,
+        content: None,
+        part: None,
+    })
+}
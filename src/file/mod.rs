@@ -1,6 +1,8 @@
 mod imp;
 
-use crate::athn_document::form;
+use crate::athn_document::{form, validation};
+use crate::form_field::{FormField, FormFieldExt};
+use crate::window::input::InputTypes;
 use adw::prelude::*;
 use adw::subclass::prelude::*;
 use glib::Object;
@@ -8,30 +10,26 @@ use gtk::{glib, FileFilter};
 
 glib::wrapper! {
     pub struct FileFormField(ObjectSubclass<imp::FileFormField>)
-        @extends gtk::Button, gtk::Widget,
-        @implements gtk::Accessible, gtk::Actionable, gtk::Buildable, gtk::ConstraintTarget;
+        @extends FormField, gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
 }
 
 impl FileFormField {
     pub fn new(form_idx: usize, id: form::ID, field: form::FileField) -> Self {
-        let label = field.global.label.unwrap_or(id.id_cloned());
+        let label = field.global.label.clone().unwrap_or(id.id_cloned());
 
         let widget: Self = Object::builder()
             .property("form-idx", form_idx as u64)
             .property("id", id.id())
-            .property("valid", field.global.optional)
+            .property("label", &label)
+            .property("optional", field.global.optional)
             .build();
+        let _ = widget.imp().field.set(field.clone());
 
-        widget.imp().label_widget.set_label(&label);
-
-        if let Some(max) = field.max {
-            widget.set_max_file_size(max.get());
-        }
-
-        if let Some(mime_types) = field.allowed_types {
+        if let Some(mime_types) = &field.allowed_types {
             let filter = FileFilter::new();
             for type_ in mime_types {
-                filter.add_mime_type(&type_);
+                filter.add_mime_type(type_);
             }
             let model = gtk::gio::ListStore::new(FileFilter::static_type());
             model.append(&filter);
@@ -39,6 +37,8 @@ impl FileFormField {
         } 
 
         widget.imp().picker.set_title(&label);
+        let validity = validation::file(&field, None);
+        widget.report_initial_value(InputTypes::File(None), validity);
 
         widget
     }
@@ -1,11 +1,12 @@
+use crate::athn_document::{form, validation};
+use crate::form_field::{declaration, FormField, FormFieldExt, FormFieldImpl};
+use crate::window::input::InputTypes;
 use adw::prelude::*;
 use adw::subclass::prelude::*;
 use glib::subclass::InitializingObject;
-use glib::subclass::Signal;
 use glib::{ParamSpec, Properties, Value};
 use gtk::{glib, CompositeTemplate, Label, SpinButton};
-use once_cell::sync::Lazy;
-use std::cell::{Cell, RefCell};
+use std::cell::{Cell, OnceCell};
 
 #[derive(Default, CompositeTemplate, Properties)]
 #[template(resource = "/online/athn/browser/gnome/float_form_field.ui")]
@@ -16,21 +17,17 @@ pub struct FloatFormField {
     #[template_child]
     pub label_widget: TemplateChild<Label>,
 
-    #[property(get, set)]
-    form_idx: Cell<u64>,
-    #[property(get, set)]
-    id: RefCell<String>,
-    #[property(get, set)]
-    label: RefCell<String>,
     #[property(get, set)]
     default: Cell<f64>,
+
+    pub field: OnceCell<form::FloatField>,
 }
 
 #[glib::object_subclass]
 impl ObjectSubclass for FloatFormField {
     const NAME: &'static str = "AthnFloatFormField";
     type Type = super::FloatFormField;
-    type ParentType = gtk::Box;
+    type ParentType = FormField;
 
     fn class_init(klass: &mut Self::Class) {
         klass.bind_template();
@@ -47,13 +44,15 @@ impl FloatFormField {
     #[template_callback]
     fn on_entry_changed(&self, entry: &SpinButton) {
         let value = &entry.value();
-        let obj = self.obj();
 
         if let Some(closest_tick) = self.closest_tick(value) {
             self.entry.set_value(closest_tick);
         }
 
-        obj.emit_by_name::<()>("updated", &[&obj.form_idx(), &obj.id(), &value, &true]);
+        let value = Some(*value);
+        let validity = validation::float(declaration(&self.field), value);
+        self.obj()
+            .update_validated_value(InputTypes::Float(value), validity);
     }
 
     pub fn closest_tick(&self, value: &f64) -> Option<f64> {
@@ -99,20 +98,13 @@ impl ObjectImpl for FloatFormField {
             .sync_create()
             .build();
     }
-
-    fn signals() -> &'static [Signal] {
-        static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
-            vec![Signal::builder("updated")
-                .param_types([
-                    u64::static_type(),
-                    String::static_type(),
-                    f64::static_type(),
-                    bool::static_type(),
-                ])
-                .build()]
-        });
-        SIGNALS.as_ref()
-    }
 }
 impl WidgetImpl for FloatFormField {}
 impl BoxImpl for FloatFormField {}
+impl FormFieldImpl for FloatFormField {
+    fn set_input(&self, value: &InputTypes) {
+        if let InputTypes::Float(Some(value)) = value {
+            self.entry.set_value(*value);
+        }
+    }
+}
@@ -1,20 +1,25 @@
 mod imp;
 
-use crate::athn_document::form;
+use crate::athn_document::{form, validation};
+use crate::form_field::{FormField, FormFieldExt};
+use crate::window::input::InputTypes;
 use adw::subclass::prelude::*;
 use glib::Object;
 use gtk::{glib, Adjustment};
 
 glib::wrapper! {
     pub struct FloatFormField(ObjectSubclass<imp::FloatFormField>)
-        @extends gtk::Box, gtk::Widget,
+        @extends FormField, gtk::Box, gtk::Widget,
         @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
 }
 
 impl FloatFormField {
     pub fn new(form_idx: usize, id: form::ID, field: form::FloatField) -> Self {
-        let label = field.global.label.unwrap_or(id.id_cloned());
-        let min = field.min.unwrap_or(f64::MIN);
+        let label = field.global.label.clone().unwrap_or(id.id_cloned());
+        let mut min = field.min.unwrap_or(f64::MIN);
+        if field.positive {
+            min = min.max(0.);
+        }
         let max = field.max.unwrap_or(f64::MAX);
         let step = field.step.unwrap_or(0.001);
         let default = field.global.default.unwrap_or(0.001);
@@ -26,6 +31,7 @@ impl FloatFormField {
             .property("label", label)
             .property("default", default)
             .build();
+        let _ = widget.imp().field.set(field.clone());
 
         let adjustment = Adjustment::new(default, min, max, step, 0., 0.);
         widget.imp().entry.set_adjustment(&adjustment);
@@ -35,6 +41,10 @@ impl FloatFormField {
         widget.imp().entry.set_range(new_min.unwrap_or(min), new_max.unwrap_or(max));
 
         widget.imp().entry.set_digits(digits);
+        // The range moves a default that is out of range into it
+        let value = Some(widget.imp().entry.value());
+        let validity = validation::float(&field, value);
+        widget.report_initial_value(InputTypes::Float(value), validity);
 
         widget
     }
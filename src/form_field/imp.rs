@@ -0,0 +1,141 @@
+use super::FormFieldClass;
+use crate::window::input::InputTypes;
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use glib::subclass::Signal;
+use glib::{ParamSpec, Properties, Value};
+use gtk::{glib, Label};
+use once_cell::sync::Lazy;
+use std::cell::{Cell, RefCell};
+
+#[derive(Default, Properties)]
+#[properties(wrapper_type = super::FormField)]
+pub struct FormField {
+    /// Shows the error message or the server error under the field
+    pub error_label: Label,
+    /// The value as it's submitted, None until the subclass reports its first value
+    pub value: RefCell<Option<InputTypes>>,
+
+    #[property(get, set)]
+    form_idx: Cell<u64>,
+    #[property(get, set)]
+    id: RefCell<String>,
+    #[property(get, set)]
+    label: RefCell<String>,
+    #[property(get, set)]
+    optional: Cell<bool>,
+    #[property(get, set = Self::valid_setter)]
+    valid: Cell<bool>,
+    /// Why the value is invalid, checked in the browser
+    #[property(get, set = Self::error_message_setter)]
+    error_message: RefCell<String>,
+    /// Why the server didnt accept the value, it's shown instead of the error message
+    #[property(get, set = Self::server_error_setter)]
+    server_error: RefCell<String>,
+    /// Why the condition of the field cant be evaluated. It's a mistake in the document, so it's
+    /// shown instead of the other messages and isnt cleared by new values
+    #[property(get, set = Self::condition_error_setter)]
+    condition_error: RefCell<String>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for FormField {
+    const NAME: &'static str = "AthnFormField";
+    const ABSTRACT: bool = true;
+    type Type = super::FormField;
+    type ParentType = gtk::Box;
+    type Class = FormFieldClass;
+
+    fn class_init(klass: &mut Self::Class) {
+        klass.set_input = |_, _| ();
+    }
+}
+
+impl FormField {
+    fn valid_setter(&self, valid: bool) {
+        self.valid.set(valid);
+        self.update_error_style();
+    }
+
+    /// Fields the server complains about are styled as errors even if they're valid here
+    fn update_error_style(&self) {
+        if self.valid.get()
+            && self.server_error.borrow().is_empty()
+            && self.condition_error.borrow().is_empty()
+        {
+            self.obj().remove_css_class("error");
+        } else {
+            self.obj().add_css_class("error");
+        }
+    }
+
+    fn error_message_setter(&self, message: String) {
+        *self.error_message.borrow_mut() = message;
+        self.update_error_label();
+    }
+
+    fn server_error_setter(&self, message: String) {
+        *self.server_error.borrow_mut() = message;
+        self.update_error_label();
+        self.update_error_style();
+    }
+
+    fn condition_error_setter(&self, message: String) {
+        *self.condition_error.borrow_mut() = message;
+        self.update_error_label();
+        self.update_error_style();
+    }
+
+    fn update_error_label(&self) {
+        let message = [
+            &self.condition_error,
+            &self.server_error,
+            &self.error_message,
+        ]
+        .into_iter()
+        .map(|message| message.borrow().clone())
+        .find(|message| !message.is_empty())
+        .unwrap_or_default();
+        self.error_label.set_visible(!message.is_empty());
+        self.error_label.set_label(&message);
+    }
+}
+
+impl ObjectImpl for FormField {
+    fn properties() -> &'static [ParamSpec] {
+        Self::derived_properties()
+    }
+
+    fn set_property(&self, id: usize, value: &Value, pspec: &ParamSpec) {
+        self.derived_set_property(id, value, pspec)
+    }
+
+    fn property(&self, id: usize, pspec: &ParamSpec) -> Value {
+        self.derived_property(id, pspec)
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        // The template children of the subclass have been added by now, so the label ends up
+        // under them
+        self.error_label.set_halign(gtk::Align::Start);
+        self.error_label.set_wrap(true);
+        self.error_label.set_margin_top(4);
+        self.error_label.add_css_class("caption");
+        self.error_label.add_css_class("error");
+        self.error_label.set_visible(false);
+        self.obj().append(&self.error_label);
+    }
+
+    fn signals() -> &'static [Signal] {
+        static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
+            vec![Signal::builder("value-changed")
+                .param_types([InputTypes::static_type()])
+                .build()]
+        });
+        SIGNALS.as_ref()
+    }
+}
+impl WidgetImpl for FormField {}
+impl BoxImpl for FormField {}
@@ -0,0 +1,166 @@
+//! The parent class of every form field widget. It holds what the fields have in common, and
+//! reports their values to the window with one signal no matter what type of field it is
+mod imp;
+
+use crate::athn_document::{form, validation};
+use crate::window::input::{Input, InputTypes};
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use glib::SignalHandlerId;
+use gtk::glib;
+use std::cell::OnceCell;
+
+use crate::boolean::BoolFormField;
+use crate::date::DateFormField;
+use crate::email::EmailFormField;
+use crate::enum_field::EnumFormField;
+use crate::file::FileFormField;
+use crate::float::FloatFormField;
+use crate::integer::IntFormField;
+use crate::list::ListFormField;
+use crate::string::StringFormField;
+use crate::tel::TelFormField;
+
+glib::wrapper! {
+    pub struct FormField(ObjectSubclass<imp::FormField>)
+        @extends gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
+}
+
+impl FormField {
+    /// Makes the widget for a form field, the other fields of the form are where lists find their
+    /// children. Submit buttons arent form fields, they return None
+    pub fn new(
+        form_idx: usize,
+        field: form::FormField,
+        form_fields: &[form::FormField],
+    ) -> Option<Self> {
+        use form::FormField::*;
+        let widget: Self = match field {
+            Integer(id, field) => IntFormField::new(form_idx, id, field).upcast(),
+            Float(id, field) => FloatFormField::new(form_idx, id, field).upcast(),
+            String(id, field) if field.variant.is_some() => {
+                EnumFormField::new(form_idx, id, field).upcast()
+            }
+            String(id, field) => StringFormField::new(form_idx, id, field).upcast(),
+            Boolean(id, field) => BoolFormField::new(form_idx, id, field).upcast(),
+            Date(id, field) => DateFormField::new(form_idx, id, field).upcast(),
+            Email(id, field) => EmailFormField::new(form_idx, id, field).upcast(),
+            File(id, field) => FileFormField::new(form_idx, id, field).upcast(),
+            Phone(id, field) => TelFormField::new(form_idx, id, field).upcast(),
+            List(id, field) => {
+                let children = form::list_children(&field, form_fields);
+                ListFormField::new(form_idx, id, field, children).upcast()
+            }
+            Submit(..) => return None,
+        };
+        Some(widget)
+    }
+
+    /// The value of the field as it is submitted
+    pub fn input(&self) -> Option<Input> {
+        Some(Input {
+            id: form::ID::new(&self.id()).ok()?,
+            value: self.imp().value.borrow().clone()?,
+            valid: self.submittable(),
+            hidden: false,
+        })
+    }
+
+    /// Whether the value can be submitted. Fields whose condition cant be evaluated never can, even
+    /// if their value is valid
+    pub fn submittable(&self) -> bool {
+        self.valid() && self.condition_error().is_empty()
+    }
+
+    /// Puts a previously entered value back into the field. Values of another type than the field
+    /// are ignored
+    pub fn set_input(&self, value: &InputTypes) {
+        (self.class().as_ref().set_input)(self, value)
+    }
+
+    pub fn connect_value_changed<F: Fn(&Self, &InputTypes) + 'static>(
+        &self,
+        callback: F,
+    ) -> SignalHandlerId {
+        self.connect_local("value-changed", false, move |values| {
+            let field = values[0].get::<Self>().expect("Emitted by a form field");
+            let value = values[1]
+                .get::<InputTypes>()
+                .expect("Carries an InputTypes");
+            callback(&field, &value);
+            None
+        })
+    }
+}
+
+/// Used by the subclasses to report their values
+pub trait FormFieldExt: IsA<FormField> {
+    /// Stores the new value and validity of the field and emits value-changed. The error the server
+    /// gave for the old value is cleared
+    fn update_value(&self, value: InputTypes, valid: bool) {
+        let field = self.upcast_ref::<FormField>();
+        if !field.server_error().is_empty() {
+            field.set_server_error(String::new());
+        }
+        field.set_valid(valid);
+        field.imp().value.replace(Some(value.clone()));
+        field.emit_by_name::<()>("value-changed", &[&value]);
+    }
+
+    /// Like update_value, but also shows why the value is invalid under the field
+    fn update_validated_value(&self, value: InputTypes, validity: validation::Validity) {
+        let field = self.upcast_ref::<FormField>();
+        field.set_error_message(validity.clone().err().unwrap_or_default());
+        self.update_value(value, validity.is_ok());
+    }
+
+    /// Reports the value the field starts out with. The field isnt marked as wrong before the user
+    /// has touched it, even if setting up the widget already reported the value
+    fn report_initial_value(&self, value: InputTypes, validity: validation::Validity) {
+        self.upcast_ref::<FormField>()
+            .set_error_message(String::new());
+        self.update_value(value, validity.is_ok());
+    }
+
+    fn form_field(&self) -> &FormField {
+        self.upcast_ref::<FormField>()
+    }
+}
+impl<O: IsA<FormField>> FormFieldExt for O {}
+
+/// The declaration a form field widget was made from. The widgets set it right after they're built,
+/// before anything can report a value, so it's only missing if that order is broken
+pub fn declaration<T>(field: &OnceCell<T>) -> &T {
+    field
+        .get()
+        .expect("Form field widgets set their declaration before anything else")
+}
+
+pub trait FormFieldImpl: BoxImpl {
+    /// Shows the value in the widget, which should then report it with update_value
+    fn set_input(&self, _value: &InputTypes) {}
+}
+
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct FormFieldClass {
+    pub parent_class: gtk::ffi::GtkBoxClass,
+    pub set_input: fn(&FormField, &InputTypes),
+}
+
+unsafe impl ClassStruct for FormFieldClass {
+    type Type = imp::FormField;
+}
+
+unsafe impl<T: FormFieldImpl> IsSubclassable<T> for FormField {
+    fn class_init(class: &mut glib::Class<Self>) {
+        Self::parent_class_init::<T>(class.upcast_ref_mut());
+
+        let klass = class.as_mut();
+        klass.set_input = |field, value| {
+            let field = unsafe { field.unsafe_cast_ref::<T::Type>() };
+            field.imp().set_input(value)
+        };
+    }
+}
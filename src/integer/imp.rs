@@ -1,34 +1,28 @@
+use crate::athn_document::{form, validation};
+use crate::form_field::{declaration, FormField, FormFieldExt, FormFieldImpl};
+use crate::window::input::InputTypes;
 use adw::prelude::*;
 use adw::subclass::prelude::*;
 use glib::subclass::InitializingObject;
-use glib::subclass::Signal;
-use glib::{ParamSpec, Properties, Value};
 use gtk::{glib, CompositeTemplate, Label, SpinButton};
-use once_cell::sync::Lazy;
-use std::cell::{Cell, RefCell};
+use std::cell::OnceCell;
 
-#[derive(Default, CompositeTemplate, Properties)]
+#[derive(Default, CompositeTemplate)]
 #[template(resource = "/online/athn/browser/gnome/int_form_field.ui")]
-#[properties(wrapper_type = super::IntFormField)]
 pub struct IntFormField {
     #[template_child]
     pub entry: TemplateChild<SpinButton>,
     #[template_child]
     pub label_widget: TemplateChild<Label>,
 
-    #[property(get, set)]
-    form_idx: Cell<u64>,
-    #[property(get, set)]
-    id: RefCell<String>,
-    #[property(get, set)]
-    label: RefCell<String>,
+    pub field: OnceCell<form::IntField>,
 }
 
 #[glib::object_subclass]
 impl ObjectSubclass for IntFormField {
     const NAME: &'static str = "AthnIntFormField";
     type Type = super::IntFormField;
-    type ParentType = gtk::Box;
+    type ParentType = FormField;
 
     fn class_init(klass: &mut Self::Class) {
         klass.bind_template();
@@ -44,26 +38,14 @@ impl ObjectSubclass for IntFormField {
 impl IntFormField {
     #[template_callback]
     fn on_entry_changed(&self, entry: &SpinButton) {
-        let value = &entry.value_as_int();
-        let obj = self.obj();
-
-        obj.emit_by_name::<()>("updated", &[&obj.form_idx(), &obj.id(), &value, &true]);
+        let value = Some(entry.value_as_int().into());
+        let validity = validation::int(declaration(&self.field), value);
+        self.obj()
+            .update_validated_value(InputTypes::Int(value), validity);
     }
 }
 
 impl ObjectImpl for IntFormField {
-    fn properties() -> &'static [ParamSpec] {
-        Self::derived_properties()
-    }
-
-    fn set_property(&self, id: usize, value: &Value, pspec: &ParamSpec) {
-        self.derived_set_property(id, value, pspec)
-    }
-
-    fn property(&self, id: usize, pspec: &ParamSpec) -> Value {
-        self.derived_property(id, pspec)
-    }
-
     fn constructed(&self) {
         self.parent_constructed();
 
@@ -72,20 +54,13 @@ impl ObjectImpl for IntFormField {
             .sync_create()
             .build();
     }
-
-    fn signals() -> &'static [Signal] {
-        static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
-            vec![Signal::builder("updated")
-                .param_types([
-                    u64::static_type(),
-                    String::static_type(),
-                    i32::static_type(),
-                    bool::static_type(),
-                ])
-                .build()]
-        });
-        SIGNALS.as_ref()
-    }
 }
 impl WidgetImpl for IntFormField {}
 impl BoxImpl for IntFormField {}
+impl FormFieldImpl for IntFormField {
+    fn set_input(&self, value: &InputTypes) {
+        if let InputTypes::Int(Some(value)) = value {
+            self.entry.set_value(*value as f64);
+        }
+    }
+}
@@ -1,20 +1,25 @@
 mod imp;
 
-use crate::athn_document::form;
+use crate::athn_document::{form, validation};
+use crate::form_field::{FormField, FormFieldExt};
+use crate::window::input::InputTypes;
 use adw::subclass::prelude::*;
 use glib::Object;
 use gtk::{glib, Adjustment};
 
 glib::wrapper! {
     pub struct IntFormField(ObjectSubclass<imp::IntFormField>)
-        @extends gtk::Box, gtk::Widget,
+        @extends FormField, gtk::Box, gtk::Widget,
         @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Orientable;
 }
 
 impl IntFormField {
     pub fn new(form_idx: usize, id: form::ID, field: form::IntField) -> Self {
-        let label = field.global.label.unwrap_or(id.id_cloned());
-        let min = field.min.unwrap_or(i64::MIN);
+        let label = field.global.label.clone().unwrap_or(id.id_cloned());
+        let mut min = field.min.unwrap_or(i64::MIN);
+        if field.positive {
+            min = min.max(0);
+        }
         let max = field.max.unwrap_or(i64::MAX);
         let step = field.step.unwrap_or(1);
         let default = field.global.default.unwrap_or(0);
@@ -24,10 +29,15 @@ impl IntFormField {
             .property("id", id.id())
             .property("label", label)
             .build();
+        let _ = widget.imp().field.set(field.clone());
 
         let adjustment =
             Adjustment::new(default as f64, min as f64, max as f64, step as f64, 1., 1.);
         widget.imp().entry.set_adjustment(&adjustment);
+        // The adjustment moves a default that is out of range into it
+        let value = Some(widget.imp().entry.value_as_int().into());
+        let validity = validation::int(&field, value);
+        widget.report_initial_value(InputTypes::Int(value), validity);
 
         widget
     }
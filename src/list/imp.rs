@@ -0,0 +1,218 @@
+use crate::athn_document::form;
+use crate::form_field::{FormField, FormFieldExt, FormFieldImpl};
+use crate::window::input::InputTypes;
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use glib::subclass::InitializingObject;
+use glib::{clone, ParamSpec, Properties, Value};
+use gtk::{glib, Button, CompositeTemplate, Label};
+use std::cell::{Cell, RefCell};
+
+/// One entry of the list with its own copy of every child field
+struct ListRow {
+    container: gtk::Box,
+    remove_button: Button,
+    fields: Vec<FormField>,
+}
+
+#[derive(Default, CompositeTemplate, Properties)]
+#[template(resource = "/online/athn/browser/gnome/list_form_field.ui")]
+#[properties(wrapper_type = super::ListFormField)]
+pub struct ListFormField {
+    #[template_child]
+    pub label_widget: TemplateChild<Label>,
+    #[template_child]
+    pub rows_box: TemplateChild<gtk::Box>,
+    #[template_child]
+    pub add_button: TemplateChild<Button>,
+
+    /// The fields every entry is made of
+    pub children: RefCell<Vec<form::FormField>>,
+    rows: RefCell<Vec<ListRow>>,
+
+    #[property(get, set)]
+    min_entries: Cell<u32>,
+    /// 0 means there is no maximum
+    #[property(get, set)]
+    max_entries: Cell<u32>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for ListFormField {
+    const NAME: &'static str = "AthnListFormField";
+    type Type = super::ListFormField;
+    type ParentType = FormField;
+
+    fn class_init(klass: &mut Self::Class) {
+        klass.bind_template();
+        klass.bind_template_callbacks();
+    }
+
+    fn instance_init(obj: &InitializingObject<Self>) {
+        obj.init_template();
+    }
+}
+
+#[gtk::template_callbacks]
+impl ListFormField {
+    #[template_callback]
+    fn on_add_clicked(&self, _button: &Button) {
+        self.add_row();
+        self.report_value();
+    }
+
+    pub fn add_row(&self) {
+        let obj = self.obj();
+        let form_idx = obj.form_field().form_idx() as usize;
+        let container = gtk::Box::builder()
+            .orientation(gtk::Orientation::Vertical)
+            .build();
+        container.add_css_class("card");
+        container.add_css_class("list-entry");
+
+        let fields: Vec<FormField> = self
+            .children
+            .borrow()
+            .iter()
+            .filter_map(|child| FormField::new(form_idx, child.clone(), &[]))
+            .collect();
+        for field in &fields {
+            field.connect_value_changed(clone!(@weak obj => move |_, _| {
+                obj.imp().report_value();
+            }));
+            container.append(field);
+        }
+
+        let remove_button = Button::builder()
+            .icon_name("user-trash-symbolic")
+            .tooltip_text("Remove")
+            .halign(gtk::Align::End)
+            .build();
+        remove_button.add_css_class("flat");
+        remove_button.connect_clicked(clone!(@weak obj, @weak container => move |_| {
+            obj.imp().remove_row(&container);
+        }));
+        container.append(&remove_button);
+
+        self.rows_box.append(&container);
+        self.rows.borrow_mut().push(ListRow {
+            container,
+            remove_button,
+            fields,
+        });
+        self.update_buttons();
+    }
+
+    fn remove_row(&self, container: &gtk::Box) {
+        self.rows
+            .borrow_mut()
+            .retain(|row| row.container != *container);
+        self.rows_box.remove(container);
+        self.update_buttons();
+        self.report_value();
+    }
+
+    /// Entries can only be added and removed while the count stays between the min and max
+    fn update_buttons(&self) {
+        let rows = self.rows.borrow();
+        let count = rows.len() as u32;
+        let max = self.obj().max_entries();
+        self.add_button.set_sensitive(max == 0 || count < max);
+        for row in rows.iter() {
+            row.remove_button
+                .set_sensitive(count > self.obj().min_entries());
+        }
+    }
+
+    /// The fields of every entry, in order
+    pub fn entry_fields(&self) -> Vec<Vec<FormField>> {
+        self.rows
+            .borrow()
+            .iter()
+            .map(|row| row.fields.clone())
+            .collect()
+    }
+
+    /// The list is only valid if every field in every entry is
+    pub fn report_value(&self) {
+        let rows = self.rows.borrow();
+        let entries: Vec<_> = rows
+            .iter()
+            .map(|row| {
+                row.fields
+                    .iter()
+                    .filter_map(|field| field.input())
+                    .collect()
+            })
+            .collect();
+        let valid = rows
+            .iter()
+            .all(|row| row.fields.iter().all(|field| field.valid()));
+        std::mem::drop(rows);
+
+        self.obj().update_value(InputTypes::List(entries), valid);
+    }
+}
+
+impl ObjectImpl for ListFormField {
+    fn properties() -> &'static [ParamSpec] {
+        Self::derived_properties()
+    }
+
+    fn set_property(&self, id: usize, value: &Value, pspec: &ParamSpec) {
+        self.derived_set_property(id, value, pspec)
+    }
+
+    fn property(&self, id: usize, pspec: &ParamSpec) -> Value {
+        self.derived_property(id, pspec)
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.bind_property::<Label>("label", self.label_widget.as_ref(), "label")
+            .sync_create()
+            .build();
+    }
+}
+impl WidgetImpl for ListFormField {}
+impl BoxImpl for ListFormField {}
+impl FormFieldImpl for ListFormField {
+    fn set_input(&self, value: &InputTypes) {
+        let InputTypes::List(entries) = value else {
+            return;
+        };
+        if self.children.borrow().is_empty() {
+            return;
+        }
+
+        let max = self.obj().max_entries();
+        let min = self.obj().min_entries() as usize;
+        while self.rows.borrow().len() < entries.len()
+            && (max == 0 || self.rows.borrow().len() < max as usize)
+        {
+            self.add_row();
+        }
+        while self.rows.borrow().len() > entries.len().max(min) {
+            let container = self.rows.borrow().last().map(|row| row.container.clone());
+            if let Some(container) = container {
+                self.remove_row(&container);
+            }
+        }
+
+        let rows = self.rows.borrow();
+        for (row, entry) in rows.iter().zip(entries) {
+            for field in &row.fields {
+                let saved = entry
+                    .iter()
+                    .find(|input| input.id.id_cloned() == field.id());
+                if let Some(saved) = saved {
+                    field.set_input(&saved.value);
+                }
+            }
+        }
+        std::mem::drop(rows);
+        self.report_value();
+    }
+}
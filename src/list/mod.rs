@@ -0,0 +1,66 @@
+mod imp;
+
+use crate::athn_document::form;
+use crate::form_field::{FormField, FormFieldExt};
+use crate::window::input::InputTypes;
+use adw::subclass::prelude::*;
+use glib::Object;
+use gtk::glib;
+
+glib::wrapper! {
+    pub struct ListFormField(ObjectSubclass<imp::ListFormField>)
+        @extends FormField, gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Orientable, gtk::Buildable, gtk::ConstraintTarget;
+}
+
+impl ListFormField {
+    /// The children are the fields that are repeated in every entry of the list, a list whose
+    /// children couldnt be found shows why instead
+    pub fn new(
+        form_idx: usize,
+        id: form::ID,
+        field: form::ListField,
+        children: Result<Vec<form::FormField>, String>,
+    ) -> Self {
+        let label = field.global.label.unwrap_or(id.id_cloned());
+        let min = field.min.map_or(0, |min| min.get());
+        let max = field.max.map_or(0, |max| max.get());
+
+        let widget: Self = Object::builder()
+            .property("form-idx", form_idx as u64)
+            .property("id", id.id())
+            .property("label", label)
+            .property("optional", field.global.optional)
+            .property("min-entries", min)
+            .property("max-entries", max)
+            .build();
+
+        let children = match children {
+            Ok(children) => children,
+            Err(message) => {
+                widget.imp().add_button.set_visible(false);
+                widget.form_field().set_error_message(message);
+                widget.update_value(InputTypes::List(vec![]), false);
+                return widget;
+            }
+        };
+        *widget.imp().children.borrow_mut() = children;
+
+        let count = field.global.default.map_or(min, |default| default.get());
+        let count = match max {
+            0 => count.max(min),
+            max => count.max(min).min(max),
+        };
+        for _ in 0..count {
+            widget.imp().add_row();
+        }
+        widget.imp().report_value();
+
+        widget
+    }
+
+    /// The fields of every entry, in order
+    pub fn entry_fields(&self) -> Vec<Vec<FormField>> {
+        self.imp().entry_fields()
+    }
+}
@@ -1,17 +1,30 @@
 mod athn_document;
+mod boolean;
 mod date;
 mod email;
 mod enum_field;
 mod file;
 mod float;
+mod form_field;
 mod integer;
+mod list;
+mod network;
+mod profile;
+mod session;
 mod string;
 mod submit;
+mod tel;
 mod window;
 
 use adw::prelude::*;
 use adw::Application;
-use gtk::gio;
+use glib::clone;
+use gtk::{gio, glib};
+use session::{Session, WindowState};
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+use url::Url;
 use window::Window;
 
 const APP_ID: &str = "online.athn.browser.gnome";
@@ -22,16 +35,301 @@ fn main() {
 
     let application = Application::builder()
         .application_id(APP_ID)
-        .flags(gio::ApplicationFlags::HANDLES_OPEN)
+        .flags(gio::ApplicationFlags::HANDLES_OPEN | gio::ApplicationFlags::HANDLES_COMMAND_LINE)
         .build();
 
-    application.connect_activate(build_ui);
+    add_command_line_options(&application);
+    application.connect_handle_local_options(handle_local_options);
+    application.connect_command_line(command_line);
+
+    application.connect_startup(setup_actions);
+    application.connect_startup(setup_network);
+    application.connect_startup(setup_session_saving);
+
+    application.connect_activate(activate);
 
     application.connect_open(open_file);
 
     application.run();
 }
 
+fn add_command_line_options(app: &Application) {
+    use glib::{Char, OptionArg, OptionFlags};
+    app.add_main_option(
+        "new-window",
+        Char::from(b'w'),
+        OptionFlags::NONE,
+        OptionArg::None,
+        "Open a new window instead of restoring the previous session",
+        None,
+    );
+    app.add_main_option(
+        "new-tab",
+        Char::from(b't'),
+        OptionFlags::NONE,
+        OptionArg::None,
+        "Open the pages in new tabs, the browser has no tabs so they open in new windows",
+        None,
+    );
+    app.add_main_option(
+        "private",
+        Char::from(b'p'),
+        OptionFlags::NONE,
+        OptionArg::None,
+        "Open the pages in private windows that arent saved in the session",
+        None,
+    );
+    app.add_main_option(
+        "profile",
+        Char::from(b'\0'),
+        OptionFlags::NONE,
+        OptionArg::Filename,
+        "Keep the settings and the session in this directory",
+        Some("DIRECTORY"),
+    );
+    app.add_main_option(
+        "dump",
+        Char::from(b'\0'),
+        OptionFlags::NONE,
+        OptionArg::String,
+        "Print the document at the URL as text without opening a window",
+        Some("URL"),
+    );
+}
+
+/// Runs in the process that was started, before the command line is sent to the instance that's
+/// already running. Returning -1 goes on with starting the browser, anything else exits with that
+/// code
+fn handle_local_options(app: &Application, options: &glib::VariantDict) -> i32 {
+    if let Ok(Some(directory)) = options.lookup::<PathBuf>("profile") {
+        if let Err(e) = std::fs::create_dir_all(&directory) {
+            eprintln!("Unable to create the profile directory: {e}");
+            return 1;
+        }
+        // The same profile has to get the same application ID wherever it's started from
+        let directory = directory.canonicalize().unwrap_or(directory);
+        app.set_application_id(Some(&profile::application_id(&directory)));
+        profile::set_profile(directory);
+    }
+
+    if let Ok(Some(url)) = options.lookup::<String>("dump") {
+        return dump(&url);
+    }
+    -1
+}
+
+/// Prints a document as text instead of showing it in a window
+fn dump(url: &str) -> i32 {
+    if let Err(e) = network::network().configure_from_settings(&profile::settings()) {
+        eprintln!("{e}");
+    }
+    match window::document_text(url) {
+        Ok(text) => {
+            println!("{text}");
+            0
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            1
+        }
+    }
+}
+
+/// Handles the command line of every launch in the instance that's running, the first launch
+/// included
+fn command_line(app: &Application, command_line: &gio::ApplicationCommandLine) -> i32 {
+    let options = command_line.options_dict();
+    let private = options.contains("private");
+    let new_window = options.contains("new-window") || options.contains("new-tab");
+    // Arguments that arent URLs are paths relative to where the browser was started
+    let uris: Vec<String> = command_line
+        .arguments()
+        .iter()
+        .skip(1)
+        .map(|argument| match Url::parse(&argument.to_string_lossy()) {
+            Ok(url) => url.to_string(),
+            Err(_) => command_line.create_file_for_arg(argument).uri().to_string(),
+        })
+        .collect();
+
+    if uris.is_empty() {
+        match (private, new_window) {
+            (true, _) => Window::new_private(app).present(),
+            (false, true) => build_ui(app),
+            (false, false) => app.activate(),
+        }
+        return 0;
+    }
+    for uri in uris {
+        let window = match private {
+            true => Window::new_private(app),
+            false => Window::new(app),
+        };
+        window.present();
+        window.set_uri(uri);
+    }
+    0
+}
+
+fn setup_actions(app: &Application) {
+    let new_window_action = gio::ActionEntry::builder("new-window")
+        .activate(|app: &Application, _, _| build_ui(app))
+        .build();
+    let quit_action = gio::ActionEntry::builder("quit")
+        .activate(|app: &Application, _, _| app.quit())
+        .build();
+    app.add_action_entries([new_window_action, quit_action]);
+
+    // Ctrl+? for the shortcuts window is set up by gtk, it's defined in resources/help-overlay.ui
+    let accels: &[(&str, &[&str])] = &[
+        ("app.new-window", &["<Ctrl>n"]),
+        ("app.quit", &["<Ctrl>q"]),
+        ("win.close", &["<Ctrl>w"]),
+        ("win.back", &["<Alt>Left"]),
+        ("win.forward", &["<Alt>Right"]),
+        ("win.reload", &["F5", "<Ctrl>r"]),
+        ("win.hard-reload", &["<Shift>F5", "<Ctrl><Shift>r"]),
+        ("win.focus-address-bar", &["<Ctrl>l", "F6"]),
+        ("win.settings", &["<Ctrl>comma"]),
+        ("win.find", &["<Ctrl>f"]),
+        ("win.view-source", &["<Ctrl>u"]),
+        ("win.print", &["<Ctrl>p"]),
+        ("win.zoom-in", &["<Ctrl>plus", "<Ctrl>equal", "<Ctrl>KP_Add"]),
+        ("win.zoom-out", &["<Ctrl>minus", "<Ctrl>KP_Subtract"]),
+        ("win.zoom-reset", &["<Ctrl>0", "<Ctrl>KP_0"]),
+    ];
+    for (action, accels) in accels {
+        app.set_accels_for_action(action, accels);
+    }
+}
+
+/// Every window shares the same HTTP client, so it's configured once here and only rebuilt when
+/// one of the settings it's built from changes
+fn setup_network(app: &Application) {
+    let settings = profile::settings();
+    if let Err(e) = network::network().configure_from_settings(&settings) {
+        eprintln!("{e}");
+    }
+    settings.connect_changed(None, |settings, key| {
+        if !network::Network::uses_setting(key) {
+            return;
+        }
+        if let Err(e) = network::network().configure_from_settings(settings) {
+            eprintln!("{e}");
+        }
+    });
+    // The changed signal stops when the settings are dropped, so they're kept until shutdown
+    app.connect_shutdown(move |_| {
+        let _ = &settings;
+    });
+}
+
+fn session_path() -> PathBuf {
+    profile::data_dir().join("session.json")
+}
+
+fn current_session(app: &Application) -> Session {
+    Session {
+        windows: app
+            .windows()
+            .iter()
+            .filter_map(|window| window.downcast_ref::<Window>())
+            .filter(|window| !window.private())
+            .map(Window::session_state)
+            .collect(),
+        clean_exit: false,
+    }
+}
+
+fn save_session(session: &Session) {
+    if let Err(e) = session.save(&session_path()) {
+        eprintln!("Unable to save the session: {e}");
+    }
+}
+
+/// The session is saved every few seconds so it survives a crash, and once more on exit
+fn setup_session_saving(app: &Application) {
+    glib::timeout_add_seconds_local(
+        session::SAVE_INTERVAL,
+        clone!(@weak app => @default-return glib::Continue(false), move || {
+            let session = current_session(&app);
+            // A session without windows would replace the one saved when the last window closed
+            if !session.windows.is_empty() {
+                save_session(&session);
+            }
+            glib::Continue(true)
+        }),
+    );
+
+    // Closing the last window quits the browser, but by then the window is already gone
+    let last_window: Rc<RefCell<Option<WindowState>>> = Rc::default();
+    app.connect_window_removed(clone!(@strong last_window => move |app, window| {
+        if let Some(window) = window.downcast_ref::<Window>() {
+            if app.windows().is_empty() && !window.private() {
+                last_window.replace(Some(window.session_state()));
+            }
+        }
+    }));
+    app.connect_shutdown(move |app| {
+        let mut session = current_session(app);
+        if session.windows.is_empty() {
+            session.windows.extend(last_window.take());
+        }
+        session.clean_exit = true;
+        save_session(&session);
+    });
+}
+
+/// The first launch restores the previous session if that's turned on in the settings, or offers
+/// to restore it if the browser crashed. Launching the browser again just opens a new window
+fn activate(app: &Application) {
+    if !app.windows().is_empty() {
+        return build_ui(app);
+    }
+    let session = Session::load(&session_path()).filter(|session| !session.is_empty());
+    let restore_session = profile::settings().boolean("restore-session");
+    match session {
+        Some(session) if !session.clean_exit => show_recovery_prompt(app, session),
+        Some(session) if restore_session => restore_windows(app, session.windows, None),
+        _ => build_ui(app),
+    }
+}
+
+fn show_recovery_prompt(app: &Application, session: Session) {
+    let window = Window::new(app);
+    window.present();
+
+    let dialog = adw::MessageDialog::builder()
+        .transient_for(&window)
+        .modal(true)
+        .heading("Restore the Previous Session?")
+        .body("The browser didnt close properly last time. The pages that were open can be opened again")
+        .build();
+    dialog.add_responses(&[("discard", "_Start New Session"), ("restore", "_Restore")]);
+    dialog.set_response_appearance("restore", adw::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("restore"));
+    dialog.set_close_response("discard");
+    dialog.connect_response(
+        None,
+        clone!(@weak app, @weak window => move |_, response| {
+            if response == "restore" {
+                restore_windows(&app, session.windows.clone(), Some(window));
+            }
+        }),
+    );
+    dialog.present();
+}
+
+/// Opens a window for every saved one, the blank window is reused for the first of them
+fn restore_windows(app: &Application, windows: Vec<WindowState>, blank_window: Option<Window>) {
+    let mut blank_window = blank_window;
+    for state in windows {
+        let window = blank_window.take().unwrap_or_else(|| Window::new(app));
+        window.present();
+        window.restore_session_state(state);
+    }
+}
+
 fn build_ui(app: &Application) {
     let window = Window::new(app);
     window.present();
@@ -0,0 +1,455 @@
+//! Every request the browser makes goes through the one client owned by the network service in
+//! here, so connections are pooled and every request is configured the same way
+pub mod proxy;
+
+use futures_util::{future, stream, StreamExt};
+use gtk::gio::Settings;
+use gtk::prelude::*;
+use once_cell::sync::Lazy;
+use proxy::Proxy;
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT_LANGUAGE, CACHE_CONTROL, PRAGMA};
+use reqwest::multipart::{Form, Part};
+use reqwest::{Body, Client, Identity, Method, RequestBuilder, StatusCode};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use tokio::sync::oneshot;
+use tokio_util::io::ReaderStream;
+use url::Url;
+
+static NETWORK: Lazy<Network> = Lazy::new(Network::new);
+
+/// How many times a failed GET request is retried when retrying is enabled
+const MAX_RETRIES: u32 = 3;
+/// The delay before the first retry, it doubles for every retry after that
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// The application wide network service
+pub fn network() -> &'static Network {
+    &NETWORK
+}
+
+#[derive(Clone, Default)]
+pub struct Config {
+    pub identity: Option<Identity>,
+    pub language: String,
+    pub proxy: Proxy,
+    pub connect_timeout: Option<Duration>,
+    /// The longest time to wait for the response headers, and then for each chunk of the body
+    pub read_timeout: Option<Duration>,
+    /// In bytes
+    pub max_document_size: Option<u64>,
+    /// Whether to retry GET requests that failed in a way that might be temporary
+    pub retry: bool,
+}
+
+pub struct Network {
+    runtime: Runtime,
+    config: RwLock<Config>,
+    client: RwLock<Client>,
+}
+
+#[derive(Debug)]
+pub struct Response {
+    pub status: StatusCode,
+    pub body: String,
+}
+
+/// A file sent in its own part of a multipart form submission
+#[derive(Debug, Clone)]
+pub struct Upload {
+    /// The name of the part, it's unique within the form and the JSON of the file refers to it
+    pub part: String,
+    pub file_name: String,
+    pub mime_type: String,
+    pub path: PathBuf,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    ConnectTimeout,
+    /// The server accepted the connection but didnt send the response headers in time
+    ResponseTimeout,
+    ReadTimeout,
+    Connect(reqwest::Error),
+    Status(StatusCode),
+    /// Contains the maximum allowed size in bytes
+    TooLarge(u64),
+    NotUtf8,
+    /// A file to upload could not be read
+    Upload(std::io::Error),
+    Other(reqwest::Error),
+}
+
+impl Error {
+    /// A short headline for the error page
+    pub fn title(&self) -> &'static str {
+        use Error::*;
+        match self {
+            ConnectTimeout => "The server could not be reached in time",
+            ResponseTimeout => "The server took too long to respond",
+            ReadTimeout => "The server stopped sending the page",
+            Connect(_) => "Unable to connect to the server",
+            Status(status) if status.is_client_error() => "The page could not be loaded",
+            Status(_) => "The server encountered an error",
+            TooLarge(_) => "The page is too large",
+            NotUtf8 => "The page is not valid UTF-8 text",
+            Upload(_) => "A file could not be uploaded",
+            Other(_) => "There was an error with the request",
+        }
+    }
+
+    /// Whether the same request might succeed if it's sent again
+    fn is_temporary(&self) -> bool {
+        use Error::*;
+        match self {
+            ConnectTimeout | ResponseTimeout | ReadTimeout | Connect(_) => true,
+            Status(status) => matches!(
+                *status,
+                StatusCode::BAD_GATEWAY
+                    | StatusCode::SERVICE_UNAVAILABLE
+                    | StatusCode::GATEWAY_TIMEOUT
+            ),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Error::*;
+        match self {
+            ConnectTimeout => write!(f, "Timed out while connecting to the server"),
+            ResponseTimeout => write!(f, "Timed out while waiting for the server to respond"),
+            ReadTimeout => write!(f, "Timed out while waiting for the server to send more data"),
+            Connect(e) | Other(e) => write!(f, "{e}"),
+            Status(status) => write!(f, "The server responded with status {status}"),
+            TooLarge(max) => write!(f, "The response is larger than the maximum allowed size of {max} bytes"),
+            NotUtf8 => write!(f, "The response contains invalid UTF-8"),
+            Upload(e) => write!(f, "A file could not be read: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() && e.is_connect() {
+            Error::ConnectTimeout
+        } else if e.is_timeout() {
+            Error::ResponseTimeout
+        } else if e.is_connect() {
+            Error::Connect(e)
+        } else {
+            Error::Other(e)
+        }
+    }
+}
+
+impl Network {
+    fn new() -> Self {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("Failed to start the network runtime.");
+        let config = Config::default();
+        let client = build_client(&config).expect("Failed to build the https client.");
+
+        Network {
+            runtime,
+            config: RwLock::new(config),
+            client: RwLock::new(client),
+        }
+    }
+
+    pub fn config(&self) -> Config {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Changes the configuration and rebuilds the client with it. If the new configuration is
+    /// invalid the old one is kept and the error is returned
+    pub fn configure(&self, change: impl FnOnce(&mut Config)) -> reqwest::Result<()> {
+        let mut config = self.config.write().unwrap();
+        let mut new_config = config.clone();
+        change(&mut new_config);
+
+        *self.client.write().unwrap() = build_client(&new_config)?;
+        *config = new_config;
+        Ok(())
+    }
+
+    /// Reads the keys that affect networking from the apps settings
+    pub fn configure_from_settings(&self, settings: &Settings) -> reqwest::Result<()> {
+        let language = settings.string("language-preference").to_string();
+        let proxy = Proxy::from_settings(settings);
+        // A value of 0 disables the limit for all of these
+        let seconds = |key: &str| {
+            let seconds = settings.int(key) as u64;
+            (seconds != 0).then(|| Duration::from_secs(seconds))
+        };
+        let connect_timeout = seconds("connect-timeout");
+        let read_timeout = seconds("read-timeout");
+        let max_document_size = settings.int("max-document-size") as u64 * 1024;
+        let retry = settings.boolean("retry-requests");
+
+        self.configure(|config| {
+            config.language = language;
+            config.proxy = proxy;
+            config.connect_timeout = connect_timeout;
+            config.read_timeout = read_timeout;
+            config.max_document_size = (max_document_size != 0).then_some(max_document_size);
+            config.retry = retry;
+        })
+    }
+
+    /// Whether a setting is one that configure_from_settings reads
+    pub fn uses_setting(key: &str) -> bool {
+        key.starts_with("proxy-")
+            || key.ends_with("-timeout")
+            || matches!(
+                key,
+                "max-document-size" | "retry-requests" | "language-preference"
+            )
+    }
+
+    fn client(&self) -> Client {
+        self.client.read().unwrap().clone()
+    }
+
+    /// Fetches a document. The request runs on the network runtime, so this can be awaited from
+    /// the glib main context
+    pub async fn get(&self, url: &Url) -> Result<String, Error> {
+        self.get_with_headers(url, HeaderMap::new()).await
+    }
+
+    /// Fetches a document like get, but asks the server and any proxies in between not to answer
+    /// with a cached response
+    pub async fn get_bypassing_cache(&self, url: &Url) -> Result<String, Error> {
+        let mut headers = HeaderMap::new();
+        headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+        // For HTTP/1.0 caches that dont know about Cache-Control
+        headers.insert(PRAGMA, HeaderValue::from_static("no-cache"));
+        self.get_with_headers(url, headers).await
+    }
+
+    async fn get_with_headers(&self, url: &Url, headers: HeaderMap) -> Result<String, Error> {
+        let client = self.client();
+        let config = self.config();
+        let url = url.clone();
+        let request = self.runtime.spawn(async move {
+            let mut attempt = 0;
+            loop {
+                match get(&client, &config, &url, headers.clone()).await {
+                    Err(e) if config.retry && attempt < MAX_RETRIES && e.is_temporary() => {
+                        tokio::time::sleep(RETRY_BACKOFF * 2u32.pow(attempt)).await;
+                        attempt += 1;
+                    }
+                    result => return result,
+                }
+            }
+        });
+        request
+            .await
+            .expect("The network runtime stopped while a request was in flight.")
+    }
+
+    /// Sends a form submission. The request runs on the network runtime, so this can be awaited
+    /// from the glib main context. POST requests are never retried because they arent idempotent
+    pub async fn post(&self, destination: String, body: String) -> Result<Response, Error> {
+        let client = self.client();
+        self.send(async move { Ok((client.post(destination).body(body), None)) })
+            .await
+    }
+
+    /// Asks the destination of a form whether it takes multipart/form-data submissions, by
+    /// looking for it in the Accept-Post header of the response to an OPTIONS request. A server
+    /// that cant be asked is assumed not to
+    pub async fn accepts_multipart(&self, destination: String) -> bool {
+        let client = self.client();
+        let config = self.config();
+        let request = self.runtime.spawn(async move {
+            let request = client.request(Method::OPTIONS, destination);
+            let Ok(response) = send_request(request, &config, None).await else {
+                return false;
+            };
+            response
+                .headers()
+                .get_all("accept-post")
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .filter_map(|media_type| media_type.split(';').next())
+                .map(str::trim)
+                .any(|media_type| media_type.eq_ignore_ascii_case("multipart/form-data"))
+        });
+        request
+            .await
+            .expect("The network runtime stopped while a request was in flight.")
+    }
+
+    /// Sends a form submission as multipart/form-data. The fields are sent as JSON in the part
+    /// named data, and every file is streamed from disk in a part of its own
+    pub async fn post_multipart(
+        &self,
+        destination: String,
+        data: String,
+        uploads: Vec<Upload>,
+    ) -> Result<Response, Error> {
+        let client = self.client();
+        self.send(async move {
+            let mut form = Form::new().part("data", Part::text(data).mime_str("application/json")?);
+            // The files are sent in order, so the body is sent once the last one has been read
+            let (sent, body_sent) = oneshot::channel();
+            let mut sent = Some(sent);
+            let last = uploads.len().saturating_sub(1);
+            for (i, upload) in uploads.into_iter().enumerate() {
+                let sent = if i == last { sent.take() } else { None };
+                form = form.part(upload.part.clone(), file_part(upload, sent).await?);
+            }
+            // Without files the body is sent as soon as the request is
+            let body_sent = sent.is_none().then_some(body_sent);
+            Ok((client.post(destination).multipart(form), body_sent))
+        })
+        .await
+    }
+
+    /// Builds the request and reads the response on the network runtime. The request can come
+    /// with a receiver that tells when its body has been sent
+    async fn send<F>(&self, request: F) -> Result<Response, Error>
+    where
+        F: std::future::Future<
+                Output = Result<(RequestBuilder, Option<oneshot::Receiver<()>>), Error>,
+            > + Send
+            + 'static,
+    {
+        let config = self.config();
+        let request = self.runtime.spawn(async move {
+            let (request, body_sent) = request.await?;
+            let response = send_request(request, &config, body_sent).await?;
+            let status = response.status();
+            Ok(Response {
+                status,
+                body: read_body(response, &config).await?,
+            })
+        });
+        request
+            .await
+            .expect("The network runtime stopped while a request was in flight.")
+    }
+}
+
+async fn get(
+    client: &Client,
+    config: &Config,
+    url: &Url,
+    headers: HeaderMap,
+) -> Result<String, Error> {
+    let response = send_request(client.get(url.clone()).headers(headers), config, None).await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(Error::Status(status));
+    }
+    read_body(response, config).await
+}
+
+/// Waits for the response headers. The read timeout only covers the body once it's being read
+/// in read_body, so without this a server that never answers would hang the request forever.
+/// When body_sent is given the timeout starts once it resolves, so an upload can take as long as
+/// it needs
+async fn send_request(
+    request: RequestBuilder,
+    config: &Config,
+    body_sent: Option<oneshot::Receiver<()>>,
+) -> Result<reqwest::Response, Error> {
+    let Some(timeout) = config.read_timeout else {
+        return Ok(request.send().await?);
+    };
+    let response = request.send();
+    tokio::pin!(response);
+    if let Some(body_sent) = body_sent {
+        tokio::select! {
+            response = &mut response => return Ok(response?),
+            // A dropped sender means the body wont be sent any further either
+            _ = body_sent => {}
+        }
+    }
+    tokio::time::timeout(timeout, response)
+        .await
+        .map_err(|_| Error::ResponseTimeout)?
+        .map_err(Error::from)
+}
+
+/// The file is read as it's sent instead of being loaded into memory first. If sent is given it's
+/// told when the whole file has been read
+async fn file_part(upload: Upload, sent: Option<oneshot::Sender<()>>) -> Result<Part, Error> {
+    let file = tokio::fs::File::open(&upload.path)
+        .await
+        .map_err(Error::Upload)?;
+    let length = file.metadata().await.map_err(Error::Upload)?.len();
+    let read = stream::once(async move {
+        if let Some(sent) = sent {
+            let _ = sent.send(());
+        }
+        None
+    })
+    .filter_map(future::ready);
+    let stream = ReaderStream::new(file).chain(read);
+    let part = Part::stream_with_length(Body::wrap_stream(stream), length)
+        .file_name(upload.file_name)
+        .mime_str(&upload.mime_type)?;
+    Ok(part)
+}
+
+/// Reads the response body chunk by chunk so that the read timeout and size limit can be enforced
+/// without holding the entire response in memory first
+async fn read_body(mut response: reqwest::Response, config: &Config) -> Result<String, Error> {
+    let max_size = config.max_document_size;
+    if let (Some(max_size), Some(length)) = (max_size, response.content_length()) {
+        if length > max_size {
+            return Err(Error::TooLarge(max_size));
+        }
+    }
+
+    let mut body = Vec::new();
+    loop {
+        let chunk = match config.read_timeout {
+            None => response.chunk().await?,
+            Some(timeout) => tokio::time::timeout(timeout, response.chunk())
+                .await
+                .map_err(|_| Error::ReadTimeout)??,
+        };
+        let Some(chunk) = chunk else { break };
+
+        body.extend_from_slice(&chunk);
+        if let Some(max_size) = max_size {
+            if body.len() as u64 > max_size {
+                return Err(Error::TooLarge(max_size));
+            }
+        }
+    }
+
+    String::from_utf8(body).map_err(|_| Error::NotUtf8)
+}
+
+fn build_client(config: &Config) -> reqwest::Result<Client> {
+    let mut headers = HeaderMap::new();
+    // An invalid language preference is left out instead of making every request fail
+    if let Ok(language) = HeaderValue::from_str(&config.language) {
+        headers.insert(ACCEPT_LANGUAGE, language);
+    }
+
+    let mut client = Client::builder()
+        .danger_accept_invalid_certs(true)
+        .default_headers(headers);
+    if let Some(timeout) = config.connect_timeout {
+        client = client.connect_timeout(timeout);
+    }
+    let client = config.proxy.apply(client)?;
+    match config.identity.clone() {
+        None => client.build(),
+        Some(identity) => client.identity(identity).build(),
+    }
+}
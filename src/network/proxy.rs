@@ -0,0 +1,108 @@
+use gtk::gio::{Settings, SettingsSchemaSource};
+use gtk::prelude::*;
+use reqwest::{ClientBuilder, NoProxy};
+
+const SYSTEM_PROXY_SCHEMA: &str = "org.gnome.system.proxy";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Proxy {
+    /// Never use a proxy, not even one from the environment
+    #[default]
+    None,
+    /// Let reqwest pick up the proxy from the usual *_PROXY environment variables
+    Environment,
+    Manual {
+        http: Option<String>,
+        https: Option<String>,
+        socks: Option<String>,
+        bypass: String,
+    },
+}
+
+impl Proxy {
+    /// Reads the proxy settings from the apps own settings, which may in turn point to the
+    /// system wide settings
+    pub fn from_settings(settings: &Settings) -> Proxy {
+        let host = settings.string("proxy-host");
+        let port = settings.int("proxy-port");
+        let bypass = settings.string("proxy-bypass").to_string();
+        match settings.string("proxy-mode").as_str() {
+            "system" => Proxy::from_system_settings(),
+            "http" if !host.is_empty() => Proxy::Manual {
+                http: Some(format!("http://{host}:{port}")),
+                https: Some(format!("http://{host}:{port}")),
+                socks: None,
+                bypass,
+            },
+            "socks5" if !host.is_empty() => Proxy::Manual {
+                http: None,
+                https: None,
+                socks: Some(format!("socks5h://{host}:{port}")),
+                bypass,
+            },
+            _ => Proxy::None,
+        }
+    }
+
+    /// Reads the gnome proxy settings, falls back to the environment if they arent installed
+    pub fn from_system_settings() -> Proxy {
+        let schema_installed = SettingsSchemaSource::default()
+            .and_then(|source| source.lookup(SYSTEM_PROXY_SCHEMA, true))
+            .is_some();
+        if !schema_installed {
+            return Proxy::Environment;
+        }
+
+        let settings = Settings::new(SYSTEM_PROXY_SCHEMA);
+        match settings.string("mode").as_str() {
+            "none" => Proxy::None,
+            "manual" => {
+                let server = |protocol: &str, scheme: &str| {
+                    let settings = settings.child(protocol);
+                    let host = settings.string("host");
+                    let port = settings.int("port");
+                    (!host.is_empty() && port != 0).then(|| format!("{scheme}://{host}:{port}"))
+                };
+                let bypass: Vec<String> = settings
+                    .strv("ignore-hosts")
+                    .iter()
+                    .map(|host| host.to_string())
+                    .collect();
+                Proxy::Manual {
+                    http: server("http", "http"),
+                    https: server("https", "http"),
+                    socks: server("socks", "socks5h"),
+                    bypass: bypass.join(","),
+                }
+            }
+            // Proxy autoconfiguration isnt supported, the environment is the best guess
+            _ => Proxy::Environment,
+        }
+    }
+
+    pub fn apply(&self, client: ClientBuilder) -> reqwest::Result<ClientBuilder> {
+        match self {
+            Proxy::None => Ok(client.no_proxy()),
+            Proxy::Environment => Ok(client),
+            Proxy::Manual {
+                http,
+                https,
+                socks,
+                bypass,
+            } => {
+                let no_proxy = || NoProxy::from_string(bypass);
+                let mut client = client;
+                if let Some(http) = http {
+                    client = client.proxy(reqwest::Proxy::http(http)?.no_proxy(no_proxy()));
+                }
+                if let Some(https) = https {
+                    client = client.proxy(reqwest::Proxy::https(https)?.no_proxy(no_proxy()));
+                }
+                if let Some(socks) = socks {
+                    client = client.proxy(reqwest::Proxy::all(socks)?.no_proxy(no_proxy()));
+                }
+                Ok(client)
+            }
+        }
+    }
+}
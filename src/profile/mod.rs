@@ -0,0 +1,83 @@
+//! Profiles keep the settings and the session of separate browser instances apart. Without a
+//! profile the settings are stored with GSettings and the session in the user data directory
+use crate::APP_ID;
+use gtk::{gio, glib};
+use once_cell::sync::OnceCell;
+use std::path::{Path, PathBuf};
+
+static PROFILE: OnceCell<PathBuf> = OnceCell::new();
+
+/// Has to be called before anything reads the settings, later calls are ignored
+pub fn set_profile(directory: PathBuf) {
+    if PROFILE.set(directory).is_err() {
+        eprintln!("The profile can only be set once");
+    }
+}
+
+/// The settings of the current profile
+pub fn settings() -> gio::Settings {
+    match PROFILE.get() {
+        None => gio::Settings::new(APP_ID),
+        Some(directory) => {
+            let backend = gio::keyfile_settings_backend_new(
+                &directory.join("settings.ini").to_string_lossy(),
+                "/online/athn/browser/gnome/",
+                None,
+            );
+            gio::Settings::with_backend(APP_ID, &backend)
+        }
+    }
+}
+
+/// Where the files of the current profile are saved
+pub fn data_dir() -> PathBuf {
+    match PROFILE.get() {
+        None => glib::user_data_dir().join(APP_ID),
+        Some(directory) => directory.clone(),
+    }
+}
+
+/// Every profile runs as its own application, so opening a window with one profile doesnt get
+/// forwarded to an instance running another. The ID has to stay the same between builds of the
+/// browser, so the path is hashed with 64 bit FNV-1a instead of the std hasher
+pub fn application_id(directory: &Path) -> String {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    let hash = directory
+        .to_string_lossy()
+        .bytes()
+        .fold(OFFSET_BASIS, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(PRIME)
+        });
+    format!("{APP_ID}.Profile{hash:016x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_application_id() {
+        let id = application_id(Path::new("/home/user/profile"));
+
+        assert!(id.starts_with(APP_ID));
+        assert!(id.rsplit('.').next().unwrap().starts_with("Profile"));
+        assert!(id.chars().all(|c| c.is_ascii_alphanumeric() || c == '.'));
+    }
+
+    #[test]
+    fn application_id_is_stable() {
+        assert_eq!(
+            application_id(Path::new("/home/user/profile")),
+            format!("{APP_ID}.Profile6302ad22cf49891b")
+        );
+    }
+
+    #[test]
+    fn profiles_have_different_ids() {
+        assert_ne!(
+            application_id(Path::new("/home/user/work")),
+            application_id(Path::new("/home/user/personal"))
+        );
+    }
+}
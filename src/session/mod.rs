@@ -0,0 +1,129 @@
+//! The open windows are saved to disk while the browser runs, so they can be restored after a
+//! restart or a crash
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// How often the session is saved while the browser is running, in seconds
+pub const SAVE_INTERVAL: u32 = 30;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct WindowState {
+    pub uri: String,
+    pub back_stack: Vec<String>,
+    pub forward_stack: Vec<String>,
+    /// Vertical scroll position of the page in pixels
+    pub scroll: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Session {
+    pub windows: Vec<WindowState>,
+    /// Only set when the browser was closed normally, a session without it was left behind by a
+    /// crash
+    pub clean_exit: bool,
+}
+
+impl Session {
+    /// Returns None if there is no saved session or it cant be read
+    pub fn load(path: &Path) -> Option<Self> {
+        let contents = fs::read_to_string(path).ok()?;
+        match serde_json::from_str(&contents) {
+            Ok(session) => Some(session),
+            Err(e) => {
+                eprintln!("Unable to read the saved session: {e}");
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Written to another file first, so a crash while saving doesnt destroy the last session
+        let temporary_path = path.with_extension("json.tmp");
+        fs::write(&temporary_path, serde_json::to_string(self)?)?;
+        fs::rename(temporary_path, path)
+    }
+
+    /// Windows that dont show a page arent worth restoring
+    pub fn is_empty(&self) -> bool {
+        self.windows.iter().all(|window| window.uri.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// A directory of its own for each test, removed with everything in it when the test ends
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new(test: &str) -> Self {
+            let path = std::env::temp_dir()
+                .join(format!("athn-session-test-{}-{test}", std::process::id()));
+            fs::create_dir_all(&path).unwrap();
+            TestDir(path)
+        }
+
+        fn path(&self, name: &str) -> PathBuf {
+            self.0.join(name)
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = TestDir::new("save_and_load");
+        let path = dir.path("session.json");
+        let session = Session {
+            windows: vec![WindowState {
+                uri: "https://athn.online/index.athn".to_string(),
+                back_stack: vec!["https://athn.online/".to_string()],
+                forward_stack: vec![],
+                scroll: 120.0,
+            }],
+            clean_exit: false,
+        };
+
+        session.save(&path).unwrap();
+
+        assert_eq!(Session::load(&path), Some(session));
+        assert!(!path.with_extension("json.tmp").exists());
+    }
+
+    #[test]
+    fn missing_session() {
+        let dir = TestDir::new("missing_session");
+
+        assert_eq!(Session::load(&dir.path("nonexistent.json")), None);
+    }
+
+    #[test]
+    fn corrupt_session() {
+        let dir = TestDir::new("corrupt_session");
+        let path = dir.path("corrupt.json");
+        fs::write(&path, "{\"windows\": [").unwrap();
+
+        assert_eq!(Session::load(&path), None);
+    }
+
+    #[test]
+    fn blank_windows_are_empty() {
+        let session = Session {
+            windows: vec![WindowState::default()],
+            clean_exit: true,
+        };
+
+        assert!(session.is_empty());
+    }
+}
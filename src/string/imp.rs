@@ -1,11 +1,14 @@
+use crate::athn_document::{form, validation};
+use crate::form_field::{declaration, FormField, FormFieldExt, FormFieldImpl};
+use crate::window::input::InputTypes;
 use adw::prelude::*;
 use adw::subclass::prelude::*;
 use glib::subclass::InitializingObject;
-use glib::subclass::Signal;
 use glib::{ParamSpec, Properties, Value};
-use gtk::{glib, CompositeTemplate, Entry, Label};
-use once_cell::sync::Lazy;
-use std::cell::{Cell, RefCell};
+use gtk::{
+    glib, CompositeTemplate, Entry, Label, PasswordEntry, ScrolledWindow, TextBuffer, TextView,
+};
+use std::cell::{Cell, OnceCell};
 
 #[derive(Default, CompositeTemplate, Properties)]
 #[template(resource = "/online/athn/browser/gnome/string_form_field.ui")]
@@ -14,27 +17,27 @@ pub struct StringFormField {
     #[template_child]
     pub entry: TemplateChild<Entry>,
     #[template_child]
+    pub password_entry: TemplateChild<PasswordEntry>,
+    #[template_child]
+    pub text_window: TemplateChild<ScrolledWindow>,
+    #[template_child]
+    pub text_view: TemplateChild<TextView>,
+    #[template_child]
     pub label_widget: TemplateChild<Label>,
 
-    #[property(get, set)]
-    form_idx: Cell<u64>,
-    #[property(get, set)]
-    id: RefCell<String>,
-    #[property(get, set)]
-    label: RefCell<String>,
-    #[property(get, set = Self::valid_setter)]
-    valid: Cell<bool>,
-    #[property(get, set)]
-    optional: Cell<bool>,
-    #[property(get, set)]
-    min_length: Cell<u32>,
+    pub field: OnceCell<form::StringField>,
+    /// Secret fields use a password entry and are never filled back in after a reload
+    #[property(get, set = Self::secret_setter)]
+    secret: Cell<bool>,
+    #[property(get, set = Self::multiline_setter)]
+    multiline: Cell<bool>,
 }
 
 #[glib::object_subclass]
 impl ObjectSubclass for StringFormField {
     const NAME: &'static str = "AthnStringFormField";
     type Type = super::StringFormField;
-    type ParentType = gtk::Box;
+    type ParentType = FormField;
 
     fn class_init(klass: &mut Self::Class) {
         klass.bind_template();
@@ -50,34 +53,55 @@ impl ObjectSubclass for StringFormField {
 impl StringFormField {
     #[template_callback]
     fn on_entry_changed(&self, entry: &Entry) {
-        let text = &entry.text();
-        let obj = self.obj();
+        self.report_text(entry.text().to_string());
+    }
+
+    #[template_callback]
+    fn on_password_entry_changed(&self, entry: &PasswordEntry) {
+        self.report_text(entry.text().to_string());
+    }
 
-        let valid = self.is_input_valid(&text);
-        if obj.valid() != valid {
-            obj.set_valid(valid)
-        };
+    #[template_callback]
+    fn on_buffer_changed(&self, buffer: &TextBuffer) {
+        let (start, end) = buffer.bounds();
+        self.report_text(buffer.text(&start, &end, false).to_string());
+    }
 
-        obj.emit_by_name::<()>("updated", &[&obj.form_idx(), &obj.id(), &text, &valid]);
+    fn report_text(&self, text: String) {
+        let validity = validation::string(declaration(&self.field), &text);
+        self.obj()
+            .update_validated_value(InputTypes::String(Some(text)), validity);
     }
 
-    fn valid_setter(&self, valid: bool) {
-        if valid {
-            self.obj().remove_css_class("error");
-        } else {
-            self.obj().add_css_class("error");
-        }
-        self.valid.set(valid);
+    fn secret_setter(&self, secret: bool) {
+        self.secret.set(secret);
+        self.show_editor();
     }
 
-    pub fn is_input_valid(&self, input: &str) -> bool {
-        if (input.len() as u32) < self.obj().min_length() {
-            return false;
-        };
-        if input.is_empty() && !self.obj().optional() {
-            return false;
-        };
-        true
+    fn multiline_setter(&self, multiline: bool) {
+        self.multiline.set(multiline);
+        self.show_editor();
+    }
+
+    /// Only one of the entry, password entry and text view is shown depending on the kind of
+    /// string. A secret that is also multiline uses the password entry
+    fn show_editor(&self) {
+        let secret = self.secret.get();
+        let multiline = self.multiline.get() && !secret;
+        self.entry.set_visible(!secret && !multiline);
+        self.password_entry.set_visible(secret);
+        self.text_window.set_visible(multiline);
+    }
+
+    /// Sets the text of whichever editor is shown
+    pub fn set_text(&self, text: &str) {
+        if self.password_entry.is_visible() {
+            self.password_entry.set_text(text);
+        } else if self.text_window.is_visible() {
+            self.text_view.buffer().set_text(text);
+        } else {
+            self.entry.set_text(text);
+        }
     }
 }
 
@@ -102,20 +126,16 @@ impl ObjectImpl for StringFormField {
             .sync_create()
             .build();
     }
-
-    fn signals() -> &'static [Signal] {
-        static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
-            vec![Signal::builder("updated")
-                .param_types([
-                    u64::static_type(),
-                    String::static_type(),
-                    String::static_type(),
-                    bool::static_type(),
-                ])
-                .build()]
-        });
-        SIGNALS.as_ref()
-    }
 }
 impl WidgetImpl for StringFormField {}
 impl BoxImpl for StringFormField {}
+impl FormFieldImpl for StringFormField {
+    fn set_input(&self, value: &InputTypes) {
+        if self.secret.get() {
+            return;
+        }
+        if let InputTypes::String(value) = value {
+            self.set_text(value.as_deref().unwrap_or_default());
+        }
+    }
+}
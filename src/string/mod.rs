@@ -1,6 +1,8 @@
 mod imp;
 
-use crate::athn_document::form;
+use crate::athn_document::{form, validation};
+use crate::form_field::{FormField, FormFieldExt};
+use crate::window::input::InputTypes;
 use adw::prelude::*;
 use adw::subclass::prelude::*;
 use glib::Object;
@@ -8,14 +10,14 @@ use gtk::glib;
 
 glib::wrapper! {
     pub struct StringFormField(ObjectSubclass<imp::StringFormField>)
-        @extends gtk::Box, gtk::Widget,
+        @extends FormField, gtk::Box, gtk::Widget,
         @implements gtk::Accessible, gtk::Orientable, gtk::Buildable, gtk::ConstraintTarget;
 }
 
 impl StringFormField {
     pub fn new(form_idx: usize, id: form::ID, field: form::StringField) -> Self {
-        let label = field.global.label.unwrap_or(id.id_cloned());
-        let default = field.global.default.unwrap_or_default();
+        let label = field.global.label.clone().unwrap_or(id.id_cloned());
+        let default = field.global.default.clone();
         let optional = field.global.optional;
 
         let min = field.min;
@@ -33,19 +35,18 @@ impl StringFormField {
             .property("label", label)
             .property("optional", optional)
             .build();
+        let _ = widget.imp().field.set(field.clone());
 
-        if let Some(min) = min {
-            widget.set_min_length(min.get());
-        }
         if let Some(max) = field.max {
             widget.imp().entry.set_max_length(max.get() as i32);
         }
+        widget.set_secret(field.secret);
+        widget.set_multiline(field.multiline);
 
-        widget.imp().entry.set_visibility(!field.secret);
-        widget.imp().entry.set_truncate_multiline(!field.multiline);
-
-        widget.imp().entry.set_text(default.as_str());
-        widget.set_valid(widget.imp().is_input_valid(&default));
+        let text = default.clone().unwrap_or_default();
+        widget.imp().set_text(&text);
+        let validity = validation::string(&field, &text);
+        widget.report_initial_value(InputTypes::String(default), validity);
 
         widget
     }
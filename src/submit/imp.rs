@@ -3,19 +3,25 @@ use adw::subclass::prelude::*;
 use glib::subclass::Signal;
 use glib::{clone, ParamSpec, Properties, Value};
 use gtk::glib;
+use crate::network::{network, Upload};
+use crate::window::input::{self, Input};
+use base64::{engine::general_purpose, Engine as _};
+use gtk::gio;
 use once_cell::sync::Lazy;
-use reqwest::{Client, Response, StatusCode, Identity};
+use reqwest::StatusCode;
 use std::cell::{Cell, RefCell};
 
+/// Files are loaded into memory to be base64 encoded for JSON submissions, bigger files can only
+/// be sent to servers that take multipart submissions
+const MAX_JSON_FILE_SIZE: u64 = 50 << 20;
+
 #[derive(Default, Properties)]
 #[properties(wrapper_type = super::SubmitFormField)]
 pub struct SubmitFormField {
     #[property(get, set)]
     pub form_idx: Cell<u64>,
-    #[property(get, set)]
-    pub serialized_data: RefCell<String>,
-    #[property(get, set)]
-    pub language_string: RefCell<String>,
+    /// The shown inputs of the form, set by the window when data-request is emitted
+    pub inputs: RefCell<Vec<Input>>,
     #[property(get, set)]
     pub destination: RefCell<String>,
     #[property(get, set)]
@@ -24,7 +30,6 @@ pub struct SubmitFormField {
     pub invalid_url: Cell<bool>,
     #[property(get, set)]
     pub invalid_form: Cell<bool>,
-    pub identity: RefCell<Option<Identity>>,
 }
 
 #[glib::object_subclass]
@@ -48,6 +53,48 @@ impl SubmitFormField {
 
         self.invalid_url.set(input);
     }
+
+    /// The body of a JSON submission, with every picked file base64 encoded in it
+    async fn json_data(&self) -> Result<String, String> {
+        let mut inputs = self.inputs.borrow().clone();
+        for file in input::files_mut(&mut inputs) {
+            let too_big = format!(
+                "The file {} is too big to be sent in this form, it can be at most 50 MiB unless the server accepts multipart/form-data uploads",
+                file.name
+            );
+            if file.size > MAX_JSON_FILE_SIZE {
+                return Err(too_big);
+            }
+            let (bytes, _) = gio::File::for_path(&file.path)
+                .load_contents_future()
+                .await
+                .map_err(|e| format!("The file {} could not be read: {e}", file.name))?;
+            // The file can have grown since it was picked
+            if bytes.len() as u64 > MAX_JSON_FILE_SIZE {
+                return Err(too_big);
+            }
+            file.content = Some(general_purpose::STANDARD.encode(bytes));
+        }
+        Ok(serde_json::to_string(&inputs).unwrap())
+    }
+
+    /// The files are sent in parts of their own, the JSON of every file has the name of its part
+    fn multipart_data(&self) -> (String, Vec<Upload>) {
+        let mut inputs = self.inputs.borrow().clone();
+        input::name_file_parts(&mut inputs);
+        let uploads = input::files(&inputs)
+            .into_iter()
+            .filter_map(|file| {
+                Some(Upload {
+                    part: file.part.clone()?,
+                    file_name: file.name.clone(),
+                    mime_type: file.mime_type.clone(),
+                    path: file.path.clone(),
+                })
+            })
+            .collect();
+        (serde_json::to_string(&inputs).unwrap(), uploads)
+    }
 }
 
 impl ObjectImpl for SubmitFormField {
@@ -90,6 +137,8 @@ impl ObjectImpl for SubmitFormField {
 impl WidgetImpl for SubmitFormField {}
 impl ButtonImpl for SubmitFormField {
     fn clicked(&self) {
+        // The window sets the inputs and whether the form is invalid while handling data-request
+        self.obj().emit_by_name::<()>("data-request", &[]);
         if self.obj().invalid_form() {
             return self.obj().emit_by_name::<()>(
                 "submit-error",
@@ -97,38 +146,32 @@ impl ButtonImpl for SubmitFormField {
             );
         }
 
-        self.obj().emit_by_name::<()>("data-request", &[]);
-
-        /*
-        let response = match post(self.obj().destination(), self.obj().serialized_data()) {
-            Ok(val) => val,
-            Err(e) => {
-                return self
-                    .obj()
-                    .emit_by_name::<()>("submit-error", &[&e.to_string()]);
-            }
-        };
-
-        self.obj()
-            .emit_by_name::<()>("submit-success", &[&response]);
-        */
-
         let ctx = glib::MainContext::default();
         ctx.spawn_local(clone!(@weak self as button => async move {
-            let language_string = button.obj().language_string();
-            let identity = button.identity.borrow().clone();
-            let response = match post(button.obj().destination(), button.obj().serialized_data(), language_string, identity) {
-                Ok(val) if val.status().is_success() => val.text().await.unwrap_or_default(),
-                Ok(e) if e.status() == StatusCode::IM_A_TEAPOT => {
+            let destination = button.obj().destination();
+            // Only forms with files are worth sending as multipart, and only if the server takes it
+            let has_files = !input::files(&button.inputs.borrow()).is_empty();
+            let response = if has_files && network().accepts_multipart(destination.clone()).await {
+                let (data, uploads) = button.multipart_data();
+                network().post_multipart(destination, data, uploads).await
+            } else {
+                let data = match button.json_data().await {
+                    Ok(data) => data,
+                    Err(e) => return button.obj().emit_by_name::<()>("submit-error", &[&e]),
+                };
+                network().post(destination, data).await
+            };
+            let response = match response {
+                Ok(val) if val.status.is_success() => val.body,
+                Ok(e) if e.status == StatusCode::IM_A_TEAPOT => {
                     return button
                         .obj()
-                        .emit_by_name::<()>("server-validation-error", &[&e.text().await.unwrap_or_default()]);
-
+                        .emit_by_name::<()>("server-validation-error", &[&e.body]);
                 }
                 Ok(e) => {
                     return button
                         .obj()
-                        .emit_by_name::<()>("submit-error", &[&e.error_for_status().unwrap_err().to_string()]);
+                        .emit_by_name::<()>("submit-error", &[&format!("The server responded with status {}", e.status)]);
                 }
                 Err(e) => {
                     return button
@@ -142,26 +185,3 @@ impl ButtonImpl for SubmitFormField {
         }));
     }
 }
-
-#[tokio::main]
-async fn post(
-    destination: String,
-    body: String,
-    language_string: String,
-    identity: Option<Identity>,
-) -> reqwest::Result<Response> {
-    let https_client = Client::builder()
-        .danger_accept_invalid_certs(true);
-    let https_client = match identity.clone() {
-        None => https_client.build()?,
-        Some(identity) => https_client.identity(identity).build()?,
-    };
-    https_client
-        .post(destination)
-        .body(body)
-        .header(reqwest::header::ACCEPT_LANGUAGE, &language_string)
-        .send()
-        .await
-    //let response = response.error_for_status()?;
-    //response.text().await
-}
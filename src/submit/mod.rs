@@ -1,9 +1,10 @@
 mod imp;
 
 use crate::athn_document::form;
+use crate::window::input::Input;
+use adw::subclass::prelude::*;
 use glib::Object;
 use gtk::glib;
-use adw::subclass::prelude::*;
 
 glib::wrapper! {
     pub struct SubmitFormField(ObjectSubclass<imp::SubmitFormField>)
@@ -19,8 +20,6 @@ impl SubmitFormField {
         label: Option<String>,
         destination: Option<String>,
         redirect: bool,
-        language_string: String,
-        identity: Option<reqwest::Identity>,
     ) -> Self {
         let label = match label {
             None => id.id(),
@@ -28,17 +27,17 @@ impl SubmitFormField {
         };
         let invalid_url = destination.is_none();
 
-        let obj: Self = Object::builder()
+        Object::builder()
             .property("form-idx", form_idx as u64)
             .property("label", label)
             .property("destination", destination.unwrap_or_default())
             .property("redirect", redirect)
             .property("invalid-url", invalid_url)
-            .property("language-string", language_string)
-            .build();
-
-        *obj.imp().identity.borrow_mut() = identity;
+            .build()
+    }
 
-        obj
+    /// The inputs that are sent when the form is submitted
+    pub fn set_inputs(&self, inputs: Vec<Input>) {
+        self.imp().inputs.replace(inputs);
     }
 }
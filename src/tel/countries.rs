@@ -0,0 +1,96 @@
+//! The countries that can be picked for a phone number, so local numbers can be written without
+//! the calling code. They're read from the metadata phonenumber parses numbers with, so every
+//! region it knows is listed
+use phonenumber::metadata::{Metadata, DATABASE};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Country {
+    /// ISO 3166-1 alpha-2 code
+    pub code: &'static str,
+    pub calling_code: u16,
+}
+
+impl Country {
+    fn from_metadata(metadata: &'static Metadata) -> Option<Country> {
+        // Numbers that dont belong to a country, like +800, are in the region 001
+        if !metadata.id().chars().all(|c| c.is_ascii_uppercase()) {
+            return None;
+        }
+        Some(Country {
+            code: metadata.id(),
+            calling_code: metadata.country_code(),
+        })
+    }
+
+    /// How the country is shown in the country selector. The metadata has no country names, so
+    /// the code is shown with the flag its regional indicator symbols make
+    pub fn display_name(&self) -> String {
+        let flag: String = self
+            .code
+            .chars()
+            .filter_map(|c| char::from_u32('🇦' as u32 + (c as u32 - 'A' as u32)))
+            .collect();
+        format!("{flag} {} (+{})", self.code, self.calling_code)
+    }
+}
+
+/// Finds a country by its code, ignoring case
+pub fn by_code(code: &str) -> Option<Country> {
+    let code = code.trim().to_ascii_uppercase();
+    DATABASE
+        .by_id(code.as_str())
+        .and_then(Country::from_metadata)
+}
+
+/// The country of a locale name like da_DK.UTF-8
+pub fn from_locale(locale: &str) -> Option<Country> {
+    let (_, territory) = locale.split_once('_')?;
+    let territory = territory.split(['.', '@']).next()?;
+    by_code(territory)
+}
+
+/// Every country in the order they're shown to the user, sorted by code
+pub fn sorted_by_code() -> Vec<Country> {
+    let mut countries: Vec<_> = DATABASE.iter().filter_map(Country::from_metadata).collect();
+    countries.sort_by_key(|country| country.code);
+    countries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_by_code() {
+        assert_eq!(by_code("dk").map(|c| c.calling_code), Some(45));
+        assert_eq!(by_code("XX"), None);
+        assert_eq!(by_code("001"), None);
+    }
+
+    #[test]
+    fn country_from_locale() {
+        assert_eq!(from_locale("da_DK.UTF-8").map(|c| c.code), Some("DK"));
+        assert_eq!(from_locale("sr_RS@latin").map(|c| c.code), Some("RS"));
+        assert_eq!(from_locale("C"), None);
+    }
+
+    #[test]
+    fn countries_share_calling_codes() {
+        let countries = sorted_by_code();
+
+        assert!(countries.windows(2).all(|pair| pair[0].code < pair[1].code));
+        assert!(countries.contains(&Country {
+            code: "CA",
+            calling_code: 1
+        }));
+        assert!(countries.contains(&Country {
+            code: "US",
+            calling_code: 1
+        }));
+    }
+
+    #[test]
+    fn display_name_has_flag() {
+        assert_eq!(by_code("DK").unwrap().display_name(), "🇩🇰 DK (+45)");
+    }
+}
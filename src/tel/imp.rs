@@ -0,0 +1,107 @@
+use super::countries::{self, Country};
+use crate::athn_document::{form, validation};
+use crate::form_field::{declaration, FormField, FormFieldExt, FormFieldImpl};
+use crate::window::input::InputTypes;
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use glib::subclass::InitializingObject;
+use gtk::{glib, CompositeTemplate, DropDown, Entry, Label, StringList};
+use std::cell::{OnceCell, RefCell};
+
+#[derive(Default, CompositeTemplate)]
+#[template(resource = "/online/athn/browser/gnome/tel_form_field.ui")]
+pub struct TelFormField {
+    #[template_child]
+    pub entry: TemplateChild<Entry>,
+    #[template_child]
+    pub country: TemplateChild<DropDown>,
+    #[template_child]
+    pub country_model: TemplateChild<StringList>,
+    #[template_child]
+    pub label_widget: TemplateChild<Label>,
+
+    /// The countries in the order of the country selector, after the international option
+    countries: RefCell<Vec<Country>>,
+    pub field: OnceCell<form::TelField>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for TelFormField {
+    const NAME: &'static str = "AthnTelFormField";
+    type Type = super::TelFormField;
+    type ParentType = FormField;
+
+    fn class_init(klass: &mut Self::Class) {
+        klass.bind_template();
+        klass.bind_template_callbacks();
+    }
+
+    fn instance_init(obj: &InitializingObject<Self>) {
+        obj.init_template();
+    }
+}
+
+#[gtk::template_callbacks]
+impl TelFormField {
+    #[template_callback]
+    fn on_entry_changed(&self, entry: &Entry) {
+        let (value, validity) = self.check_number(&entry.text());
+        self.obj()
+            .update_validated_value(InputTypes::Tel(value), validity);
+    }
+
+    #[template_callback]
+    fn on_country_changed(&self, _pspec: &glib::ParamSpec, _country: &DropDown) {
+        self.on_entry_changed(&self.entry);
+    }
+
+    /// The first option is for numbers that are always written with their calling code
+    pub fn fill_countries(&self, selected: Option<&Country>) {
+        let countries = countries::sorted_by_code();
+        self.country_model.append("International");
+        for country in &countries {
+            self.country_model.append(&country.display_name());
+        }
+        let selected_idx = selected
+            .and_then(|selected| countries.iter().position(|country| country == selected))
+            .map_or(0, |i| i as u32 + 1);
+        *self.countries.borrow_mut() = countries;
+        self.country.set_selected(selected_idx);
+    }
+
+    fn selected_country(&self) -> Option<Country> {
+        let selected = self.country.selected().checked_sub(1)?;
+        self.countries.borrow().get(selected as usize).copied()
+    }
+
+    /// Returns the number in the E.164 format and whether it's valid
+    pub fn check_number(&self, text: &str) -> (Option<String>, validation::Validity) {
+        let number = match text.trim().is_empty() {
+            true => None,
+            false => super::normalize(text, self.selected_country().as_ref()),
+        };
+        let validity = validation::tel(declaration(&self.field), text, number.as_deref());
+        (number, validity)
+    }
+}
+
+impl ObjectImpl for TelFormField {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.bind_property::<Label>("label", self.label_widget.as_ref(), "label")
+            .sync_create()
+            .build();
+    }
+}
+impl WidgetImpl for TelFormField {}
+impl BoxImpl for TelFormField {}
+impl FormFieldImpl for TelFormField {
+    fn set_input(&self, value: &InputTypes) {
+        // Saved numbers have their calling code, so the country doesnt matter
+        if let InputTypes::Tel(Some(value)) = value {
+            self.entry.set_text(value);
+        }
+    }
+}
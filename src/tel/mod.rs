@@ -0,0 +1,101 @@
+pub mod countries;
+mod imp;
+
+use crate::athn_document::form;
+use crate::form_field::{FormField, FormFieldExt};
+use crate::window::input::InputTypes;
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use countries::Country;
+use glib::Object;
+use gtk::glib;
+use phonenumber::{country::Id as CountryId, Mode};
+
+glib::wrapper! {
+    pub struct TelFormField(ObjectSubclass<imp::TelFormField>)
+        @extends FormField, gtk::Box, gtk::Widget,
+        @implements gtk::Accessible, gtk::Orientable, gtk::Buildable, gtk::ConstraintTarget;
+}
+
+impl TelFormField {
+    pub fn new(form_idx: usize, id: form::ID, field: form::TelField) -> Self {
+        let label = field.global.label.clone().unwrap_or(id.id_cloned());
+
+        let widget: Self = Object::builder()
+            .property("form-idx", form_idx as u64)
+            .property("id", id.id())
+            .property("label", label)
+            .property("optional", field.global.optional)
+            .build();
+        let _ = widget.imp().field.set(field.clone());
+
+        // The country of the document is a better guess than the users own
+        let country = field
+            .country
+            .as_deref()
+            .and_then(countries::by_code)
+            .or_else(|| {
+                glib::language_names()
+                    .first()
+                    .and_then(|locale| countries::from_locale(locale))
+            });
+        widget.imp().fill_countries(country.as_ref());
+
+        let default = field.global.default.clone().unwrap_or_default();
+        widget.imp().entry.set_text(&default);
+        let (value, validity) = widget.imp().check_number(&default);
+        widget.report_initial_value(InputTypes::Tel(value), validity);
+
+        widget
+    }
+}
+
+/// Turns a phone number into the E.164 format, like +4512345678. Numbers without a calling code
+/// are read as local numbers of the country. Returns None if it isnt a valid phone number
+pub fn normalize(number: &str, country: Option<&Country>) -> Option<String> {
+    let country = country.and_then(|country| country.code.parse::<CountryId>().ok());
+    let number = phonenumber::parse(country, number).ok()?;
+    if !phonenumber::is_valid(&number) {
+        return None;
+    }
+    Some(number.format().mode(Mode::E164).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_international_number() {
+        assert_eq!(
+            normalize("+44 113 496 0000", None),
+            Some("+441134960000".to_string())
+        );
+    }
+
+    #[test]
+    fn normalize_local_number() {
+        assert_eq!(
+            normalize("(0113) 496 0000", countries::by_code("GB").as_ref()),
+            Some("+441134960000".to_string())
+        );
+    }
+
+    #[test]
+    fn calling_code_overrides_country() {
+        assert_eq!(
+            normalize("+44 113 496 0000", countries::by_code("DK").as_ref()),
+            Some("+441134960000".to_string())
+        );
+    }
+
+    #[test]
+    fn invalid_numbers() {
+        assert_eq!(normalize("113 496 0000", None), None);
+        assert_eq!(normalize("+44 1", None), None);
+        assert_eq!(
+            normalize("not a number", countries::by_code("GB").as_ref()),
+            None
+        );
+    }
+}
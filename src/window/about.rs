@@ -0,0 +1,204 @@
+//! The browsers internal about: pages, they are generated as documents so that they are rendered
+//! the same way as any other page
+use crate::athn_document::line_types::{AdmonitionType, FooterLine, Level, Link, MainLine};
+use crate::athn_document::{Document, DocumentBuilder, Metadata};
+
+/// Everything the about pages show that isnt known at compile time
+pub struct AboutData<'a> {
+    pub history: &'a [String],
+    pub bookmarks: &'a [String],
+    /// Every setting as its description and its current value
+    pub settings: &'a [(String, String)],
+}
+
+/// Returns None if there's no about page with that name
+pub fn generate(page: &str, data: AboutData) -> Option<Document> {
+    let document = match page {
+        "blank" => Document::builder(),
+        "history" => link_list_page(
+            "History",
+            "Pages visited in this window, most recent first",
+            "You havent visited any pages yet",
+            data.history.iter().rev(),
+        ),
+        "bookmarks" => link_list_page(
+            "Bookmarks",
+            "Your saved pages",
+            "You dont have any bookmarks yet",
+            data.bookmarks.iter(),
+        ),
+        "downloads" => downloads_page(),
+        "settings" => settings_page(data.settings),
+        "version" => version_page(),
+        _ => return None,
+    };
+    Some(document.build())
+}
+
+fn page_builder(title: &str, subtitle: &str) -> DocumentBuilder {
+    let mut builder = Document::builder();
+    builder.metadata = Metadata::builder()
+        .title(title.to_string())
+        .subtitle(subtitle.to_string());
+    builder
+}
+
+fn link_list_page<'a>(
+    title: &str,
+    subtitle: &str,
+    empty_message: &str,
+    urls: impl Iterator<Item = &'a String>,
+) -> DocumentBuilder {
+    let mut builder = page_builder(title, subtitle);
+    let mut is_empty = true;
+    for url in urls {
+        is_empty = false;
+        builder = builder.add_main_line(MainLine::LinkLine(Link {
+            url: url.clone(),
+            label: None,
+        }));
+    }
+    if is_empty {
+        builder = builder.add_main_line(MainLine::TextLine(empty_message.to_string()));
+    }
+    builder
+}
+
+fn downloads_page() -> DocumentBuilder {
+    page_builder("Downloads", "Files saved by the browser").add_main_line(
+        MainLine::AdmonitionLine(
+            AdmonitionType::Note,
+            "This browser only displays ATHN documents, it doesnt download files".to_string(),
+        ),
+    )
+}
+
+fn settings_page(settings: &[(String, String)]) -> DocumentBuilder {
+    // The settings panel is made of widgets that cant be expressed in a document
+    let mut builder = page_builder("Settings", "The current settings of the browser")
+        .add_main_line(MainLine::AdmonitionLine(
+            AdmonitionType::Note,
+            "Change the settings with the settings button or Ctrl+Comma".to_string(),
+        ))
+        .add_main_line(MainLine::HeadingLine(
+            Level::Two,
+            "Current settings".to_string(),
+        ));
+    for (description, value) in settings {
+        builder = builder.add_main_line(MainLine::UListLine(
+            Level::One,
+            format!("{description}: {value}"),
+        ));
+    }
+    builder
+}
+
+fn version_page() -> DocumentBuilder {
+    let version_line = |name: &str, version: String| {
+        MainLine::UListLine(Level::One, format!("{name}: {version}"))
+    };
+
+    page_builder(
+        "ATHN browser",
+        "ATHN reference browser for the gnome ecosystem",
+    )
+    .add_main_line(MainLine::HeadingLine(Level::Two, "Versions".to_string()))
+    .add_main_line(version_line(
+        "Browser",
+        env!("CARGO_PKG_VERSION").to_string(),
+    ))
+    .add_main_line(version_line(
+        "GTK",
+        format!(
+            "{}.{}.{}",
+            gtk::major_version(),
+            gtk::minor_version(),
+            gtk::micro_version()
+        ),
+    ))
+    .add_main_line(version_line(
+        "Libadwaita",
+        format!(
+            "{}.{}.{}",
+            adw::major_version(),
+            adw::minor_version(),
+            adw::micro_version()
+        ),
+    ))
+    .add_main_line(MainLine::HeadingLine(Level::Two, "Links".to_string()))
+    .add_main_line(MainLine::LinkLine(Link {
+        url: env!("CARGO_PKG_HOMEPAGE").to_string(),
+        label: Some("Project ATHN".to_string()),
+    }))
+    .add_main_line(MainLine::LinkLine(Link {
+        url: env!("CARGO_PKG_REPOSITORY").to_string(),
+        label: Some("Source code".to_string()),
+    }))
+    .add_footer_line(FooterLine::TextLine(format!(
+        "Licensed under {}",
+        env!("CARGO_PKG_LICENSE")
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate_with(
+        page: &str,
+        history: &[String],
+        settings: &[(String, String)],
+    ) -> Option<Document> {
+        let data = AboutData {
+            history,
+            bookmarks: &[],
+            settings,
+        };
+        generate(page, data)
+    }
+
+    #[test]
+    fn unknown_page() {
+        assert!(generate_with("nonexistent", &[], &[]).is_none());
+    }
+
+    #[test]
+    fn history_most_recent_first() {
+        let history = vec![
+            "https://athn.online/".to_string(),
+            "https://athn.online/index.athn".to_string(),
+        ];
+
+        let document = generate_with("history", &history, &[]).unwrap();
+
+        assert_eq!(
+            document.main[0],
+            MainLine::LinkLine(Link {
+                url: "https://athn.online/index.athn".to_string(),
+                label: None,
+            })
+        );
+    }
+
+    #[test]
+    fn empty_bookmarks() {
+        let document = generate_with("bookmarks", &[], &[]).unwrap();
+
+        assert_eq!(
+            document.main,
+            vec![MainLine::TextLine("You dont have any bookmarks yet".to_string())]
+        );
+    }
+
+    #[test]
+    fn settings_list_current_values() {
+        let settings = vec![("Restore the session".to_string(), "true".to_string())];
+
+        let document = generate_with("settings", &[], &settings).unwrap();
+
+        assert_eq!(
+            document.main[2],
+            MainLine::UListLine(Level::One, "Restore the session: true".to_string())
+        );
+    }
+}
@@ -0,0 +1,87 @@
+//! Text matching for find in page, the widgets are searched and highlighted by the window
+use std::ops::Range;
+
+/// Returns the byte ranges of every case insensitive occurrence of the query in the text.
+/// Matches dont overlap
+pub fn find_matches(text: &str, query: &str) -> Vec<Range<usize>> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return vec![];
+    }
+
+    // Lowercasing can turn one char into several, so every lowercased char remembers the byte
+    // range of the char it came from
+    let text: Vec<(char, Range<usize>)> = text
+        .char_indices()
+        .flat_map(|(i, c)| c.to_lowercase().map(move |lower| (lower, i..i + c.len_utf8())))
+        .collect();
+
+    let mut matches = vec![];
+    let mut i = 0;
+    while i + query.len() <= text.len() {
+        let candidate = &text[i..i + query.len()];
+        if candidate.iter().map(|(c, _)| c).eq(query.iter()) {
+            matches.push(candidate[0].1.start..candidate[query.len() - 1].1.end);
+            i += query.len();
+        } else {
+            i += 1;
+        }
+    }
+    matches
+}
+
+/// Text buffers count in chars instead of bytes
+pub fn char_offset(text: &str, byte_offset: usize) -> i32 {
+    text[..byte_offset].chars().count() as i32
+}
+
+/// Returns the index of the match after or before the current one, wrapping around at the ends
+pub fn step(current: Option<usize>, count: usize, forwards: bool) -> Option<usize> {
+    if count == 0 {
+        return None;
+    }
+    Some(match (current, forwards) {
+        (None, true) => 0,
+        (None, false) => count - 1,
+        (Some(current), true) => (current + 1) % count,
+        (Some(current), false) => (current + count - 1) % count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn case_insensitive() {
+        assert_eq!(find_matches("ATHN is athn", "Athn"), vec![0..4, 8..12]);
+    }
+
+    #[test]
+    fn matches_dont_overlap() {
+        assert_eq!(find_matches("aaaa", "aa"), vec![0..2, 2..4]);
+    }
+
+    #[test]
+    fn multibyte_chars() {
+        let text = "Grüße aus Ørsted";
+        let matches = find_matches(text, "ørsted");
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(&text[matches[0].clone()], "Ørsted");
+        assert_eq!(char_offset(text, matches[0].start), 10);
+    }
+
+    #[test]
+    fn empty_query() {
+        assert!(find_matches("text", "").is_empty());
+    }
+
+    #[test]
+    fn step_wraps_around() {
+        assert_eq!(step(Some(2), 3, true), Some(0));
+        assert_eq!(step(Some(0), 3, false), Some(2));
+        assert_eq!(step(None, 3, false), Some(2));
+        assert_eq!(step(None, 0, true), None);
+    }
+}
@@ -1,20 +1,32 @@
 use crate::athn_document;
-use crate::athn_document::{Document, ParserState};
+use crate::athn_document::{form, Document, ParserState};
+use crate::network::{self, network};
+use crate::profile;
+use crate::window::about::{self, AboutData};
+use crate::window::find;
 use crate::window::input::Input;
+use crate::window::local::{get_document_by_file, FileError};
+use crate::window::print::{self, BlockStyle, PrintBlock};
+use crate::window::search::{self, SearchProvider};
+use crate::window::source::VIEW_SOURCE_PREFIX;
+use crate::window::typography::{self, DEFAULT_ZOOM, ZOOM_LEVELS};
 use adw::prelude::*;
 use adw::subclass::prelude::*;
-use adw::{Leaflet, ButtonContent};
+use adw::{ButtonContent, ExpanderRow, Leaflet};
 use core::fmt::Debug;
 use gio::{Settings, File};
 use glib::subclass::InitializingObject;
 use glib::{ParamSpec, Properties, Value, clone};
 use gtk::{
-    gio, glib, Button, CompositeTemplate, Entry, Label, ListBox, ScrolledWindow, SearchEntry,
-    Stack, TextBuffer, TextTagTable,
+    gdk, gio, glib, pango, Button, CheckButton, CompositeTemplate, DropDown, Entry,
+    Label, ListBox, ScrolledWindow, SearchBar, SearchEntry, SpinButton, Stack, TextBuffer, TextTag,
+    TextTagTable, TextView,
 };
 use reqwest::Identity;
-use std::cell::RefCell;
-use std::fs;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::rc::Rc;
 use url::Url;
 
 #[derive(Properties, CompositeTemplate, Default)]
@@ -30,26 +42,116 @@ pub struct Window {
     #[template_child]
     pub stack: TemplateChild<Stack>,
     #[template_child]
+    pub request_error_page: TemplateChild<adw::StatusPage>,
+    #[template_child]
     pub request_error: TemplateChild<Label>,
     #[template_child]
     pub toaster: TemplateChild<adw::ToastOverlay>,
     #[template_child]
+    pub find_bar: TemplateChild<SearchBar>,
+    #[template_child]
+    pub find_entry: TemplateChild<SearchEntry>,
+    #[template_child]
+    pub find_count_label: TemplateChild<Label>,
+    #[template_child]
+    pub canvas_window: TemplateChild<ScrolledWindow>,
+    #[template_child]
     pub canvas: TemplateChild<ListBox>,
     #[template_child]
     pub text_block_tag_table: TemplateChild<TextTagTable>,
     #[template_child]
-    pub server_error_window: TemplateChild<ScrolledWindow>,
+    pub source_window: TemplateChild<ScrolledWindow>,
+    #[template_child]
+    pub source_view: TemplateChild<TextView>,
     #[template_child]
-    pub server_error_buffer: TemplateChild<TextBuffer>,
+    pub source_line_numbers: TemplateChild<Label>,
+    #[template_child]
+    pub source_tag_table: TemplateChild<TextTagTable>,
     #[template_child]
     pub language_preference_entry: TemplateChild<Entry>,
     #[template_child]
     pub client_cert_label: TemplateChild<ButtonContent>,
+    #[template_child]
+    pub proxy_mode_dropdown: TemplateChild<DropDown>,
+    #[template_child]
+    pub manual_proxy_box: TemplateChild<gtk::Box>,
+    #[template_child]
+    pub proxy_host_entry: TemplateChild<Entry>,
+    #[template_child]
+    pub proxy_port_entry: TemplateChild<SpinButton>,
+    #[template_child]
+    pub proxy_bypass_entry: TemplateChild<Entry>,
+    #[template_child]
+    pub connect_timeout_entry: TemplateChild<SpinButton>,
+    #[template_child]
+    pub read_timeout_entry: TemplateChild<SpinButton>,
+    #[template_child]
+    pub max_document_size_entry: TemplateChild<SpinButton>,
+    #[template_child]
+    pub retry_requests_button: TemplateChild<CheckButton>,
+    #[template_child]
+    pub live_reload_button: TemplateChild<CheckButton>,
+    #[template_child]
+    pub live_reload_directory_button: TemplateChild<CheckButton>,
+    #[template_child]
+    pub restore_session_button: TemplateChild<CheckButton>,
+    #[template_child]
+    pub body_font_entry: TemplateChild<Entry>,
+    #[template_child]
+    pub monospace_font_entry: TemplateChild<Entry>,
+    #[template_child]
+    pub max_content_width_entry: TemplateChild<SpinButton>,
+    #[template_child]
+    pub search_provider_list: TemplateChild<ListBox>,
+    #[template_child]
+    pub search_provider_name_entry: TemplateChild<Entry>,
+    #[template_child]
+    pub search_provider_keyword_entry: TemplateChild<Entry>,
+    #[template_child]
+    pub search_provider_template_entry: TemplateChild<Entry>,
     #[property(get, set = Self::go_to_url)]
     pub uri: RefCell<String>,
+    /// Private windows arent saved in the session and dont remember zoom levels
+    #[property(get, set, construct_only)]
+    pub private: Cell<bool>,
     pub form_data: RefCell<Vec<Vec<Input>>>,
+    /// The fields of every form on the page, in the order they're declared
+    pub form_fields: RefCell<Vec<Vec<form::FormField>>>,
     pub settings: RefCell<Option<Settings>>,
-    pub client_cert: RefCell<Option<Identity>>,
+    pub file_monitor: RefCell<Option<gio::FileMonitor>>,
+    /// Every page visited in this window, oldest first
+    pub history: RefCell<Vec<String>>,
+    pub back_stack: RefCell<Vec<String>>,
+    pub forward_stack: RefCell<Vec<String>>,
+    /// Set while going back or forward, so the navigation stacks are left alone
+    pub navigating_history: Cell<bool>,
+    /// Counts the pages that started loading, a document that arrives after the next page started
+    /// loading is thrown away
+    pub current_load: Cell<u64>,
+    /// Index into ZOOM_LEVELS
+    pub zoom_level: Cell<usize>,
+    /// Applies the font settings, it's added to the display while the window exists
+    pub typography_provider: gtk::CssProvider,
+    /// The last document that failed to parse, so its source can be viewed from the parse error
+    /// page
+    pub invalid_source: RefCell<String>,
+    pub find_matches: RefCell<Vec<FindMatch>>,
+    pub find_current: Cell<Option<usize>>,
+    /// The attributes of the labels with highlighted matches from before they were highlighted,
+    /// like the bold weight of form field labels
+    pub find_label_attributes: RefCell<Vec<(Label, Option<pango::AttrList>)>>,
+    /// The current page laid out for printing
+    pub print_blocks: RefCell<Vec<PrintBlock>>,
+    /// Remembers the choices made in the print dialog for the next time
+    pub print_settings: RefCell<Option<gtk::PrintSettings>>,
+}
+
+/// A piece of text on the canvas that matches the find in page query
+pub struct FindMatch {
+    /// Either a Label or a TextView
+    widget: gtk::Widget,
+    /// In bytes
+    range: Range<usize>,
 }
 
 // Boilerplate
@@ -69,8 +171,12 @@ impl ObjectSubclass for Window {
     }
 }
 
+/// The values of the proxy-mode setting in the same order as in the proxy dropdown
+const PROXY_MODES: [&str; 4] = ["none", "system", "http", "socks5"];
+
 fn validate_url(url: &str) -> Result<Url, url::ParseError> {
-    let has_supported_protocol = url.starts_with("https://") || url.starts_with("file://");
+    let has_supported_protocol =
+        url.starts_with("https://") || url.starts_with("file://") || url.starts_with("about:");
     if has_supported_protocol {
         Url::parse(url)
     } else {
@@ -79,75 +185,132 @@ fn validate_url(url: &str) -> Result<Url, url::ParseError> {
     }
 }
 
-fn get_document(url: &Url, language_string: &str, identity: &Option<Identity>) -> Result<String, String> {
-    match url.scheme() {
-        "https" => get_document_by_https(url, language_string, identity).map_err(|e| e.to_string()),
-        "file" => get_document_by_file(url).map_err(|e| e.to_string()),
-        _ => Err("Unsupported protocol".to_string()),
+/// Fetches and parses a document and lays it out as plain text, without a window
+pub fn document_text(input: &str) -> Result<String, String> {
+    let url = validate_url(input).map_err(|e| format!("Invalid URL: {e}"))?;
+    // Nothing else is running on the main context yet, so the request is just waited for
+    let source = glib::MainContext::default()
+        .block_on(get_document(&url, false))
+        .map_err(|e| format!("{}: {}", e.title, e.message))?;
+    let document =
+        athn_document::parse(source.lines(), Document::builder(), ParserState::default())
+            .map_err(|e| format!("Unable to parse the document: {e}"))?
+            .build();
+    Ok(print::plain_text(&print::print_blocks(&document, &url)))
+}
+
+/// A page that was just rendered hasnt been laid out yet, so the position would still be out of
+/// range
+fn scroll_after_layout(adjustment: gtk::Adjustment, scroll: Option<f64>) {
+    if let Some(scroll) = scroll {
+        glib::idle_add_local_once(move || adjustment.set_value(scroll));
     }
 }
 
-fn get_document_by_file(url: &Url) -> Result<String, std::io::Error> {
-    // TODO: use url.to_file_path()
-    fs::read_to_string(url.path())
+/// In points, the body font setting only sets the family
+const PRINT_FONT_SIZE: u32 = 11;
+
+/// Names of the text tags that highlight matches in text blocks and code blocks
+const FIND_MATCH_TAG: &str = "find-match";
+const FIND_CURRENT_TAG: &str = "find-current";
+
+/// What went wrong while fetching a document, shown on the request error page
+pub struct RequestError {
+    pub title: &'static str,
+    pub message: String,
 }
 
-fn get_document_by_https(url: &Url, language_string: &str, identity: &Option<Identity>) -> reqwest::Result<String> {
-    let https_client = reqwest::blocking::Client::builder()
-        .danger_accept_invalid_certs(true);
-    let https_client = match identity.clone() {
-        None => https_client.build()?,
-        Some(identity) => https_client.identity(identity).build()?,
-    };
+impl From<network::Error> for RequestError {
+    fn from(e: network::Error) -> Self {
+        RequestError {
+            title: e.title(),
+            message: e.to_string(),
+        }
+    }
+}
 
-    let response = https_client
-        .get(url.clone())
-        .header(reqwest::header::ACCEPT_LANGUAGE, language_string)
-        .send()?;
+impl From<FileError> for RequestError {
+    fn from(e: FileError) -> Self {
+        RequestError {
+            title: match e {
+                FileError::NotUtf8(_) => "This file is not a text document",
+                _ => "Unable to read the file",
+            },
+            message: e.to_string(),
+        }
+    }
+}
 
-    response.text()
+/// Local files are always read from disk, bypass_cache only affects https
+async fn get_document(url: &Url, bypass_cache: bool) -> Result<String, RequestError> {
+    match url.scheme() {
+        "https" if bypass_cache => Ok(network().get_bypassing_cache(url).await?),
+        "https" => Ok(network().get(url).await?),
+        "file" => Ok(get_document_by_file(url)?),
+        _ => Err(RequestError {
+            title: "Unsupported protocol",
+            message: format!("The {} protocol is not supported", url.scheme()),
+        }),
+    }
 }
 
 #[gtk::template_callbacks]
 impl Window {
     fn go_to_url(&self, input: String) {
+        self.open_uri(input, None);
+    }
+
+    /// Goes to a page like setting the uri property, and scrolls to scroll once it's shown
+    pub fn open_uri(&self, input: String, scroll: Option<f64>) {
+        self.start_load();
+        self.leave_page(&input);
+
         self.stack.set_visible_child_name("canvas");
         let start_time = std::time::Instant::now();
 
         self.search_entry.set_text(&input);
 
+        if let Some(url) = input.strip_prefix(VIEW_SOURCE_PREFIX) {
+            return self.view_source(url, scroll);
+        }
+
         let url = validate_url(&input);
         let url = match url {
-            Err(e) => return self.set_request_error(&e.to_string()),
+            Err(e) => {
+                return self.set_request_error(&RequestError {
+                    title: "Invalid URL",
+                    message: e.to_string(),
+                })
+            }
             Ok(val) => val,
         };
 
         // Sets the actual value in the window object, syntax referenced from https://gtk-rs.org/gtk-rs-core/stable/latest/docs/glib/derive.Properties.html#example
         *self.uri.borrow_mut() = url.to_string();
         *self.form_data.borrow_mut() = vec![];
+        self.watch_local_file(&url);
 
-        let language_string = self
-            .settings
-            .borrow()
-            .clone()
-            .map(|settings| settings.string("language-preference"))
-            .unwrap_or_default();
-        let identity = self.client_cert.borrow();
+        if url.scheme() == "about" {
+            self.show_about_page(&url);
+            return scroll_after_layout(self.canvas_window.vadjustment(), scroll);
+        }
 
-        let response = get_document(&url, &language_string, &*identity);
-        let response = match response {
-            Err(e) => return self.set_request_error(&e),
-            Ok(val) => val,
-        };
+        self.load(url.clone(), false, move |window, response| {
+            let response = match response {
+                Err(e) => return window.set_request_error(&e),
+                Ok(val) => val,
+            };
+            window.history.borrow_mut().push(url.to_string());
 
-        let request_time = start_time.elapsed();
+            let request_time = start_time.elapsed();
 
-        self.render_document(&response, &url);
+            window.render_document(&response, &url);
+            scroll_after_layout(window.canvas_window.vadjustment(), scroll);
 
-        // Timing stuff, dont mind me
-        let total_time = start_time.elapsed();
-        println!(
-            "
+            // Timing stuff, dont mind me
+            let total_time = start_time.elapsed();
+            println!(
+                "
         ╭─────────────────┬─────────
         │ Request timing breakdown:
         ├─────────────────┼─────────
@@ -157,10 +320,45 @@ impl Window {
         │ \x1b[1mTotal\x1b[0m           │ \x1b[1m{:?}\x1b[0m
         ╰─────────────────┴─────────
         ",
-            request_time,
-            total_time - request_time,
-            total_time
-        );
+                request_time,
+                total_time - request_time,
+                total_time
+            );
+        });
+    }
+
+    /// Puts the current page on the back stack when going to another one, unless the page is left
+    /// by going back or forward
+    fn leave_page(&self, next: &str) {
+        let previous = self.uri.borrow().clone();
+        if !self.navigating_history.get() && !previous.is_empty() && previous != next {
+            self.back_stack.borrow_mut().push(previous);
+            self.forward_stack.borrow_mut().clear();
+            self.update_navigation_actions();
+        }
+    }
+
+    /// Makes the page that was loading before stale, so it isnt shown once it arrives
+    fn start_load(&self) -> u64 {
+        let load = self.current_load.get() + 1;
+        self.current_load.set(load);
+        load
+    }
+
+    /// Fetches a document without blocking the window and hands it to loaded, unless another page
+    /// started loading in the meantime
+    fn load<F>(&self, url: Url, bypass_cache: bool, loaded: F)
+    where
+        F: FnOnce(&Self, Result<String, RequestError>) + 'static,
+    {
+        let load = self.start_load();
+        let ctx = glib::MainContext::default();
+        ctx.spawn_local(clone!(@weak self as window => async move {
+            let document = get_document(&url, bypass_cache).await;
+            if window.current_load.get() == load {
+                loaded(&window, document);
+            }
+        }));
     }
 
     pub fn render_document(&self, document_string: &str, base_url: &Url) {
@@ -172,28 +370,807 @@ impl Window {
         let document = match document {
             Err(e) => {
                 eprintln!("{e}");
+                *self.invalid_source.borrow_mut() = document_string.to_string();
                 return self.stack.set_visible_child_name("parse-error");
             }
             Ok(val) => val.build(),
         };
 
-        self.server_error_window.set_visible(false);
         self.obj().render(document, base_url);
     }
 
-    fn set_request_error(&self, err_message: &str) {
+    /// Fetches and renders the current page again, keeping the scroll position and the data
+    /// entered into forms
+    pub fn reload_preserving_state(&self) {
+        let uri = self.uri.borrow().clone();
+        if uri.starts_with(VIEW_SOURCE_PREFIX) {
+            let scroll_position = self.source_window.vadjustment().value();
+            return self.open_uri(uri, Some(scroll_position));
+        }
+
+        let url = match Url::parse(&self.uri.borrow()) {
+            Err(_) => return,
+            Ok(val) => val,
+        };
+        if url.scheme() == "about" {
+            self.start_load();
+            return self.show_about_page(&url);
+        }
+        self.load(url.clone(), false, move |window, document| {
+            let document = match document {
+                Err(e) => return window.set_request_error(&e),
+                Ok(val) => val,
+            };
+
+            let adjustment = window.canvas_window.vadjustment();
+            let scroll_position = adjustment.value();
+            let form_data = window.form_data.take();
+
+            window.stack.set_visible_child_name("canvas");
+            window.render_document(&document, &url);
+            if window
+                .stack
+                .visible_child_name()
+                .is_some_and(|name| name == "parse-error")
+            {
+                // Keep the form data around for when the document is fixed
+                *window.form_data.borrow_mut() = form_data;
+                return;
+            }
+            window.obj().restore_form_data(form_data);
+
+            scroll_after_layout(adjustment, Some(scroll_position));
+        });
+    }
+
+    /// Fetches the current page again without using any cache and renders it from scratch,
+    /// throwing away the data entered into forms
+    pub fn hard_reload(&self) {
+        let uri = self.uri.borrow().clone();
+        if let Some(source_url) = uri.strip_prefix(VIEW_SOURCE_PREFIX) {
+            return self.hard_reload_source(source_url);
+        }
+        let url = match Url::parse(&uri) {
+            Err(_) => return,
+            Ok(val) => val,
+        };
+        if url.scheme() == "about" {
+            return self.go_to_url(uri);
+        }
+        self.load(url.clone(), true, move |window, document| {
+            let document = match document {
+                Err(e) => return window.set_request_error(&e),
+                Ok(val) => val,
+            };
+
+            *window.form_data.borrow_mut() = vec![];
+            window.stack.set_visible_child_name("canvas");
+            window.render_document(&document, &url);
+            window.canvas_window.vadjustment().set_value(0.0);
+        });
+    }
+
+    fn hard_reload_source(&self, source_url: &str) {
+        let url = match validate_url(source_url) {
+            Err(_) => return,
+            Ok(val) => val,
+        };
+        self.load(url, true, |window, source| {
+            let source = match source {
+                Err(e) => return window.set_request_error(&e),
+                Ok(val) => val,
+            };
+            window.obj().render_source(&source);
+            window.source_window.vadjustment().set_value(0.0);
+        });
+    }
+
+    /// Shows the source of a document instead of rendering it
+    fn view_source(&self, input: &str, scroll: Option<f64>) {
+        let url = match validate_url(input) {
+            Err(e) => {
+                return self.set_request_error(&RequestError {
+                    title: "Invalid URL",
+                    message: e.to_string(),
+                })
+            }
+            Ok(val) => val,
+        };
+        let uri = format!("{VIEW_SOURCE_PREFIX}{url}");
+        *self.uri.borrow_mut() = uri.clone();
+        self.watch_local_file(&url);
+
+        self.load(url, false, move |window, source| {
+            let source = match source {
+                Err(e) => return window.set_request_error(&e),
+                Ok(val) => val,
+            };
+            window.history.borrow_mut().push(uri);
+            window.obj().render_source(&source);
+            scroll_after_layout(window.source_window.vadjustment(), scroll);
+        });
+    }
+
+    /// Goes to the previous page if back is true, otherwise goes to the next one
+    fn navigate_history(&self, back: bool) {
+        let (from, to) = match back {
+            true => (&self.back_stack, &self.forward_stack),
+            false => (&self.forward_stack, &self.back_stack),
+        };
+        let Some(url) = from.borrow_mut().pop() else {
+            return;
+        };
+        to.borrow_mut().push(self.uri.borrow().clone());
+        self.update_navigation_actions();
+
+        self.navigating_history.set(true);
+        self.obj().set_uri(url);
+        self.navigating_history.set(false);
+    }
+
+    pub fn update_navigation_actions(&self) {
+        let set_enabled = |name: &str, enabled: bool| {
+            if let Some(action) = self
+                .obj()
+                .lookup_action(name)
+                .and_downcast::<gio::SimpleAction>()
+            {
+                action.set_enabled(enabled);
+            }
+        };
+        set_enabled("back", !self.back_stack.borrow().is_empty());
+        set_enabled("forward", !self.forward_stack.borrow().is_empty());
+    }
+
+    /// Sizes on the canvas are set in em in style.css, so scaling the font size scales everything
+    fn set_zoom_level(&self, level: usize) {
+        let level = level.min(ZOOM_LEVELS.len() - 1);
+        self.canvas
+            .remove_css_class(&format!("zoom-{}", ZOOM_LEVELS[self.zoom_level.get()]));
+        self.canvas
+            .add_css_class(&format!("zoom-{}", ZOOM_LEVELS[level]));
+        self.zoom_level.set(level);
+        self.apply_max_content_width();
+    }
+
+    /// Zooms and remembers the zoom level for the current site
+    fn change_zoom_level(&self, level: usize) {
+        self.set_zoom_level(level);
+        if self.private.get() {
+            return;
+        }
+
+        let Some(site) = typography::zoom_site(&self.uri.borrow()) else {
+            return;
+        };
+        let settings = self.settings.borrow();
+        let Some(settings) = settings.as_ref() else {
+            return;
+        };
+        let mut site_zoom = site_zoom(settings);
+        match ZOOM_LEVELS[self.zoom_level.get()] {
+            DEFAULT_ZOOM => site_zoom.remove(&site),
+            zoom => site_zoom.insert(site, zoom),
+        };
+        if let Err(e) = settings.set_value("site-zoom", &site_zoom.to_variant()) {
+            eprintln!("{e}");
+        }
+    }
+
+    /// Goes back to the zoom level that was used the last time the current site was visited
+    pub fn apply_site_zoom(&self) {
+        let zoom = typography::zoom_site(&self.uri.borrow())
+            .zip(self.settings.borrow().as_ref().map(site_zoom))
+            .and_then(|(site, site_zoom)| site_zoom.get(&site).copied())
+            .unwrap_or(DEFAULT_ZOOM);
+        self.set_zoom_level(typography::zoom_level(zoom));
+    }
+
+    /// The maximum width is scaled along with the zoom level, so zooming doesnt change how many
+    /// words fit on a line
+    fn apply_max_content_width(&self) {
+        let width = self
+            .settings
+            .borrow()
+            .as_ref()
+            .map(|settings| settings.int("max-content-width"))
+            .unwrap_or(1000);
+        let width = match width {
+            0 => -1,
+            width => width * ZOOM_LEVELS[self.zoom_level.get()] as i32 / 100,
+        };
+        self.canvas_window.set_max_content_width(width);
+    }
+
+    fn update_typography(&self) {
+        let settings = self.settings.borrow();
+        let Some(settings) = settings.as_ref() else {
+            return;
+        };
+        let body_font = settings.string("body-font");
+        let monospace_font = settings.string("monospace-font");
+        self.typography_provider
+            .load_from_data(&typography::typography_css(&body_font, &monospace_font));
+
+        // Inline code in text blocks is styled with a text tag instead of css
+        if let Some(tag) = self.text_block_tag_table.lookup("preformatted") {
+            match monospace_font.trim() {
+                "" => tag.set_family(Some("Monospace")),
+                font => tag.set_family(Some(font)),
+            }
+        }
+    }
+
+    fn focus_address_bar(&self) {
+        self.search_entry.grab_focus();
+        self.search_entry.select_region(0, -1);
+    }
+
+    fn setup_actions(&self) {
+        use gio::ActionEntry;
+        type Window = super::Window;
+
+        let actions = [
+            ActionEntry::builder("back")
+                .activate(|window: &Window, _, _| window.imp().navigate_history(true))
+                .build(),
+            ActionEntry::builder("forward")
+                .activate(|window: &Window, _, _| window.imp().navigate_history(false))
+                .build(),
+            ActionEntry::builder("reload")
+                .activate(|window: &Window, _, _| window.imp().reload_preserving_state())
+                .build(),
+            ActionEntry::builder("hard-reload")
+                .activate(|window: &Window, _, _| window.imp().hard_reload())
+                .build(),
+            ActionEntry::builder("focus-address-bar")
+                .activate(|window: &Window, _, _| window.imp().focus_address_bar())
+                .build(),
+            ActionEntry::builder("settings")
+                .activate(|window: &Window, _, _| {
+                    window.imp().stack.set_visible_child_name("settings")
+                })
+                .build(),
+            ActionEntry::builder("close")
+                .activate(|window: &Window, _, _| window.close())
+                .build(),
+            ActionEntry::builder("find")
+                .activate(|window: &Window, _, _| window.imp().show_find_bar())
+                .build(),
+            ActionEntry::builder("print")
+                .activate(|window: &Window, _, _| window.imp().print())
+                .build(),
+            ActionEntry::builder("view-source")
+                .activate(|window: &Window, _, _| window.imp().toggle_view_source())
+                .build(),
+            ActionEntry::builder("zoom-in")
+                .activate(|window: &Window, _, _| {
+                    let imp = window.imp();
+                    imp.change_zoom_level(imp.zoom_level.get() + 1);
+                })
+                .build(),
+            ActionEntry::builder("zoom-out")
+                .activate(|window: &Window, _, _| {
+                    let imp = window.imp();
+                    imp.change_zoom_level(imp.zoom_level.get().saturating_sub(1));
+                })
+                .build(),
+            ActionEntry::builder("zoom-reset")
+                .activate(|window: &Window, _, _| {
+                    window
+                        .imp()
+                        .change_zoom_level(typography::zoom_level(DEFAULT_ZOOM))
+                })
+                .build(),
+        ];
+        self.obj().add_action_entries(actions);
+        self.update_navigation_actions();
+    }
+
+    /// Opens the print dialog, which can also save the page as a PDF with "Print to File". The
+    /// document is laid out again for paper instead of printing the widgets on the canvas
+    fn print(&self) {
+        let visible_page = self.stack.visible_child_name();
+        if !matches!(visible_page.as_deref(), Some("canvas" | "source")) {
+            let toast = adw::Toast::new("There is no document to print");
+            self.toaster.add_toast(toast);
+            if let Some(toast_widget) = self.toaster.last_child() {
+                toast_widget.add_css_class("error");
+            }
+            return;
+        }
+
+        let uri = self.uri.borrow().clone();
+        let print_settings = self.print_settings.borrow().clone().unwrap_or_else(|| {
+            let print_settings = gtk::PrintSettings::new();
+            // Print to file saves a PDF named after the document
+            let name = uri
+                .rsplit('/')
+                .next()
+                .map(|name| name.trim_end_matches(".athn"))
+                .filter(|name| !name.is_empty())
+                .unwrap_or("document");
+            print_settings.set("output-basename", Some(name));
+            print_settings.set("output-file-format", Some("pdf"));
+            print_settings
+        });
+        let operation = gtk::PrintOperation::builder()
+            .job_name(uri)
+            .print_settings(&print_settings)
+            .embed_page_setup(true)
+            .build();
+
+        let body_font = self
+            .settings
+            .borrow()
+            .as_ref()
+            .map(|settings| settings.string("body-font").trim().to_string())
+            .filter(|font| !font.is_empty())
+            .unwrap_or("Sans".to_string());
+        let font = pango::FontDescription::from_string(&format!("{body_font} {PRINT_FONT_SIZE}"));
+        let blocks = self.print_blocks.borrow().clone();
+        let pages: Rc<RefCell<Vec<Vec<PrintItem>>>> = Rc::default();
+
+        operation.connect_begin_print(clone!(@strong pages => move |operation: &gtk::PrintOperation, context: &gtk::PrintContext| {
+            let laid_out = paginate(context, &blocks, &font);
+            operation.set_n_pages(laid_out.len() as i32);
+            pages.replace(laid_out);
+        }));
+        operation.connect_draw_page(move |_, context, page| {
+            let pages = pages.borrow();
+            let Some(items) = pages.get(page as usize) else {
+                return;
+            };
+            let cairo = context.cairo_context();
+            for item in items {
+                match item {
+                    PrintItem::Line {
+                        layout,
+                        index,
+                        x,
+                        y,
+                    } => {
+                        let Some(line) = layout.line_readonly(*index) else {
+                            continue;
+                        };
+                        cairo.move_to(*x, *y);
+                        pangocairo::functions::show_layout_line(&cairo, &line);
+                    }
+                    PrintItem::Rule { y } => {
+                        cairo.set_line_width(0.5);
+                        cairo.move_to(0.0, *y);
+                        cairo.line_to(context.width(), *y);
+                        if let Err(e) = cairo.stroke() {
+                            eprintln!("{e}");
+                        }
+                    }
+                }
+            }
+        });
+
+        match operation.run(gtk::PrintOperationAction::PrintDialog, Some(&*self.obj())) {
+            Ok(gtk::PrintOperationResult::Apply) => {
+                self.print_settings.replace(operation.print_settings());
+            }
+            Ok(_) => (),
+            Err(e) => {
+                let toast = adw::Toast::new(&format!("Unable to print: {e}"));
+                self.toaster.add_toast(toast);
+                if let Some(toast_widget) = self.toaster.last_child() {
+                    toast_widget.add_css_class("error");
+                }
+            }
+        }
+    }
+
+    /// Switches between the rendered document and its source
+    pub fn toggle_view_source(&self) {
+        let uri = self.uri.borrow().clone();
+        if uri.is_empty() {
+            return;
+        }
+        match uri.strip_prefix(VIEW_SOURCE_PREFIX) {
+            Some(url) => self.obj().set_uri(url),
+            None => self.obj().set_uri(format!("{VIEW_SOURCE_PREFIX}{uri}")),
+        }
+    }
+
+    /// Internal pages are generated instead of fetched, except for the settings panel
+    fn show_about_page(&self, url: &Url) {
+        let bookmarks: Vec<String> = self
+            .settings
+            .borrow()
+            .as_ref()
+            .map(|settings| {
+                settings
+                    .strv("bookmarks")
+                    .iter()
+                    .map(|bookmark| bookmark.to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let history = self.history.borrow().clone();
+        let settings = self.settings_summary();
+        let data = AboutData {
+            history: &history,
+            bookmarks: &bookmarks,
+            settings: &settings,
+        };
+
+        match about::generate(url.path(), data) {
+            None => self.set_request_error(&RequestError {
+                title: "Unknown internal page",
+                message: format!("{url} does not exist"),
+            }),
+            Some(document) => {
+                self.stack.set_visible_child_name("canvas");
+                self.obj().render(document, url);
+            }
+        }
+    }
+
+    /// Every setting as its summary from the schema and its current value, sorted by key. Private
+    /// windows show it too, so the settings that record browsing are left out
+    fn settings_summary(&self) -> Vec<(String, String)> {
+        let settings = self.settings.borrow();
+        let Some(settings) = settings.as_ref() else {
+            return vec![];
+        };
+        let Some(schema) = settings.settings_schema() else {
+            return vec![];
+        };
+        let mut keys = schema.list_keys();
+        keys.sort();
+        keys.iter()
+            .filter(|key| !matches!(key.as_str(), "site-zoom" | "bookmarks"))
+            .map(|key| {
+                let description = schema
+                    .key(key)
+                    .summary()
+                    .map_or(key.to_string(), |summary| summary.to_string());
+                (description, settings.value(key).print(false).to_string())
+            })
+            .collect()
+    }
+
+    /// Watches local documents for changes so that they can be reloaded while they are being
+    /// written. Stops watching the previous document
+    fn watch_local_file(&self, url: &Url) {
+        use gio::{FileMonitorEvent::*, FileMonitorFlags, FileQueryInfoFlags, FileType};
+
+        *self.file_monitor.borrow_mut() = None;
+
+        let settings = self.settings.borrow().clone();
+        let settings = match settings {
+            Some(settings) if settings.boolean("live-reload") => settings,
+            _ => return,
+        };
+        if url.scheme() != "file" {
+            return;
+        }
+
+        let file = File::for_uri(url.as_str());
+        let file_type =
+            file.query_file_type(FileQueryInfoFlags::NONE, None::<&gio::Cancellable>);
+        let monitor = if file_type == FileType::Directory {
+            file.monitor_directory(FileMonitorFlags::WATCH_MOVES, None::<&gio::Cancellable>)
+        } else if settings.boolean("live-reload-directory") {
+            match file.parent() {
+                None => return,
+                Some(directory) => directory
+                    .monitor_directory(FileMonitorFlags::WATCH_MOVES, None::<&gio::Cancellable>),
+            }
+        } else {
+            file.monitor_file(FileMonitorFlags::WATCH_MOVES, None::<&gio::Cancellable>)
+        };
+        let monitor = match monitor {
+            Err(e) => return eprintln!("Unable to watch {url} for changes: {e}"),
+            Ok(val) => val,
+        };
+
+        let is_listing = file_type == FileType::Directory;
+        monitor.connect_changed(clone!(@weak self as window => move |_, changed, other, event| {
+            // Editors that save by replacing the file cause a rename instead of a change. Created
+            // files are always followed by a ChangesDoneHint, reacting to both would reload twice
+            if !matches!(event, ChangesDoneHint | MovedIn | Renamed) {
+                return;
+            }
+            // A directory that's watched for a document also reports the other files in it
+            let is_document = changed.equal(&file) || other.is_some_and(|other| other.equal(&file));
+            if is_listing || is_document {
+                window.reload_preserving_state();
+            }
+        }));
+        *self.file_monitor.borrow_mut() = Some(monitor);
+    }
+
+    fn set_request_error(&self, error: &RequestError) {
         self.stack.set_visible_child_name("request-error");
-        self.request_error.set_label(err_message);
+        self.request_error_page.set_title(error.title);
+        self.request_error.set_label(&error.message);
+    }
+
+    pub fn show_find_bar(&self) {
+        self.find_bar.set_search_mode(true);
+        self.find_entry.grab_focus();
+    }
+
+    /// Searches the canvas for the text in the find bar, highlights the matches and scrolls to
+    /// the first one. Clears the matches if the find bar is closed
+    pub fn find_in_page(&self) {
+        self.clear_find_highlights();
+
+        let query = match self.find_bar.is_search_mode() {
+            true => self.find_entry.text().to_string(),
+            false => String::new(),
+        };
+        let mut matches = vec![];
+        collect_find_matches(self.canvas.upcast_ref(), &query, &mut matches);
+
+        self.find_current.set(find::step(None, matches.len(), true));
+        *self.find_matches.borrow_mut() = matches;
+        self.show_find_matches(&query);
+    }
+
+    fn step_find_match(&self, forwards: bool) {
+        self.clear_find_highlights();
+        let count = self.find_matches.borrow().len();
+        self.find_current
+            .set(find::step(self.find_current.get(), count, forwards));
+        self.show_find_matches(&self.find_entry.text());
+    }
+
+    fn show_find_matches(&self, query: &str) {
+        let matches = self.find_matches.borrow();
+        let current = self.find_current.get();
+        for (i, find_match) in matches.iter().enumerate() {
+            if let Some(label) = find_match.widget.downcast_ref::<Label>() {
+                let mut saved = self.find_label_attributes.borrow_mut();
+                if !saved.iter().any(|(saved, _)| saved == label) {
+                    saved.push((label.clone(), label.attributes()));
+                }
+            }
+            highlight_find_match(find_match, Some(i) == current);
+        }
+
+        let count = match (current, matches.len()) {
+            _ if query.is_empty() => String::new(),
+            (_, 0) | (None, _) => "No matches".to_string(),
+            (Some(current), count) => format!("{} of {count}", current + 1),
+        };
+        self.find_count_label.set_label(&count);
+        if !query.is_empty() && matches.is_empty() {
+            self.find_entry.add_css_class("error");
+        } else {
+            self.find_entry.remove_css_class("error");
+        }
+
+        let Some(find_match) = current.and_then(|current| matches.get(current)) else {
+            return;
+        };
+        // Matches in collapsed dropdowns have to be revealed before they can be scrolled to
+        if let Some(expander) = find_match
+            .widget
+            .ancestor(ExpanderRow::static_type())
+            .and_downcast::<ExpanderRow>()
+        {
+            expander.set_expanded(true);
+        }
+        let widget = find_match.widget.clone();
+        let range = find_match.range.clone();
+        // Wait for the expanded dropdown to be laid out
+        glib::idle_add_local_once(clone!(@weak self as window => move || {
+            window.scroll_to_find_match(&widget, range);
+        }));
+    }
+
+    fn scroll_to_find_match(&self, widget: &gtk::Widget, range: Range<usize>) {
+        let y = match widget.downcast_ref::<TextView>() {
+            // Text blocks can be long, so scroll to the line of the match within it
+            Some(text_view) => {
+                let buffer = text_view.buffer();
+                let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+                let iter = buffer.iter_at_offset(find::char_offset(&text, range.start));
+                super::text_iter_y(text_view, &iter)
+            }
+            None => 0.0,
+        };
+        super::scroll_to_widget(&self.canvas_window, widget, y);
+    }
+
+    fn clear_find_highlights(&self) {
+        for (label, attributes) in self.find_label_attributes.take() {
+            label.set_attributes(attributes.as_ref());
+        }
+        for find_match in self.find_matches.borrow().iter() {
+            if let Some(text_view) = find_match.widget.downcast_ref::<TextView>() {
+                let buffer = text_view.buffer();
+                let (start, end) = buffer.bounds();
+                for name in [FIND_MATCH_TAG, FIND_CURRENT_TAG] {
+                    if let Some(tag) = buffer.tag_table().lookup(name) {
+                        buffer.remove_tag(&tag, &start, &end);
+                    }
+                }
+            }
+        }
+    }
+
+    #[template_callback]
+    fn on_find_entry_changed(&self, _: &SearchEntry) {
+        self.find_in_page();
+    }
+
+    #[template_callback]
+    fn on_find_next(&self, _: &SearchEntry) {
+        self.step_find_match(true);
+    }
+
+    #[template_callback]
+    fn on_find_previous(&self, _: &SearchEntry) {
+        self.step_find_match(false);
+    }
+
+    #[template_callback]
+    fn on_find_next_pressed(&self, _: &Button) {
+        self.step_find_match(true);
+    }
+
+    #[template_callback]
+    fn on_find_previous_pressed(&self, _: &Button) {
+        self.step_find_match(false);
+    }
+
+    #[template_callback]
+    fn on_find_bar_toggled(&self, _pspec: &ParamSpec, _: &SearchBar) {
+        self.find_in_page();
     }
 
     pub fn is_form_valid(&self, form_idx: usize) -> bool {
         let data = self.form_data.borrow();
-        data[form_idx].iter().find(|e| e.valid == false).is_none()
+        data[form_idx]
+            .iter()
+            .find(|e| e.valid == false && !e.hidden)
+            .is_none()
     }
 
     #[template_callback]
     fn on_search_entry_activate(&self, search_entry: &gtk::SearchEntry) {
-        self.obj().set_uri(search_entry.text());
+        let resolved = search::resolve_input(
+            &search_entry.text(),
+            &self.search_providers(),
+            &self.default_search_keyword(),
+        );
+        match resolved {
+            Ok(url) => self.obj().set_uri(url),
+            Err(e) => self.set_request_error(&RequestError {
+                title: "Unable to search",
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    fn search_providers(&self) -> Vec<SearchProvider> {
+        self.settings
+            .borrow()
+            .as_ref()
+            .and_then(|settings| {
+                settings
+                    .value("search-providers")
+                    .get::<Vec<(String, String, String)>>()
+            })
+            .unwrap_or_default()
+            .into_iter()
+            .map(SearchProvider::from)
+            .collect()
+    }
+
+    fn default_search_keyword(&self) -> String {
+        self.settings
+            .borrow()
+            .as_ref()
+            .map(|settings| settings.string("default-search-provider").to_string())
+            .unwrap_or_default()
+    }
+
+    fn set_search_providers(&self, providers: &[SearchProvider]) {
+        let providers: Vec<(String, String, String)> =
+            providers.iter().map(|provider| provider.into()).collect();
+        if let Some(settings) = self.settings.borrow().as_ref() {
+            if let Err(e) = settings.set_value("search-providers", &providers.to_variant()) {
+                eprintln!("{e}");
+            }
+        }
+    }
+
+    fn rebuild_search_provider_list(&self) {
+        super::clear_list_box(&self.search_provider_list);
+
+        let providers = self.search_providers();
+        let default_keyword = self.default_search_keyword();
+        // Falls back to the first provider, just like searching does
+        let default_keyword = providers
+            .iter()
+            .find(|provider| provider.keyword == default_keyword)
+            .or(providers.first())
+            .map(|provider| provider.keyword.clone())
+            .unwrap_or_default();
+
+        let mut group: Option<CheckButton> = None;
+        for provider in providers {
+            let row = adw::ActionRow::builder()
+                .title(format!("{} ({})", provider.name, provider.keyword))
+                .subtitle(&provider.template)
+                .use_markup(false)
+                .build();
+
+            let default_button = CheckButton::builder()
+                .valign(gtk::Align::Center)
+                .tooltip_text("Use as the default search provider")
+                .build();
+            // Grouped before activating so only one button in the group ends up active
+            default_button.set_group(group.as_ref());
+            default_button.set_active(provider.keyword == default_keyword);
+            let keyword = provider.keyword.clone();
+            default_button.connect_toggled(clone!(@weak self as window => move |button| {
+                if !button.is_active() {
+                    return;
+                }
+                if let Some(settings) = window.settings.borrow().as_ref() {
+                    if let Err(e) = settings.set_string("default-search-provider", &keyword) {
+                        eprintln!("{e}");
+                    }
+                }
+            }));
+            row.add_prefix(&default_button);
+
+            let remove_button = Button::builder()
+                .icon_name("entry-clear-symbolic")
+                .valign(gtk::Align::Center)
+                .tooltip_text("Remove search provider")
+                .build();
+            remove_button.add_css_class("flat");
+            let keyword = provider.keyword.clone();
+            remove_button.connect_clicked(clone!(@weak self as window => move |_| {
+                let mut providers = window.search_providers();
+                providers.retain(|provider| provider.keyword != keyword);
+                window.set_search_providers(&providers);
+            }));
+            row.add_suffix(&remove_button);
+
+            self.search_provider_list.append(&row);
+            group.get_or_insert(default_button);
+        }
+    }
+
+    #[template_callback]
+    fn on_add_search_provider(&self, _: &Button) {
+        let provider = SearchProvider {
+            name: self.search_provider_name_entry.text().trim().to_string(),
+            keyword: self.search_provider_keyword_entry.text().trim().to_string(),
+            template: self
+                .search_provider_template_entry
+                .text()
+                .trim()
+                .to_string(),
+        };
+        if let Err(e) = search::validate_provider(&provider) {
+            let toast = adw::Toast::new(e);
+            self.toaster.add_toast(toast);
+            if let Some(toast_widget) = self.toaster.last_child() {
+                toast_widget.add_css_class("error");
+            }
+            return;
+        }
+
+        // A provider with the same keyword is replaced
+        let mut providers = self.search_providers();
+        providers.retain(|existing| existing.keyword != provider.keyword);
+        providers.push(provider);
+        self.set_search_providers(&providers);
+
+        self.search_provider_name_entry.set_text("");
+        self.search_provider_keyword_entry.set_text("");
+        self.search_provider_template_entry.set_text("");
     }
 
     #[template_callback]
@@ -203,6 +1180,20 @@ impl Window {
         launcher.launch(None::<&gtk::Window>, None::<&gtk::gio::Cancellable>, |_| ());
     }
 
+    #[template_callback]
+    fn on_parse_error_view_source_clicked(&self, _button: &Button) {
+        // The source that failed to parse is shown instead of fetching it again, but it's still
+        // navigated to like any other page
+        let uri = format!("{VIEW_SOURCE_PREFIX}{}", self.uri.borrow());
+        self.start_load();
+        self.leave_page(&uri);
+        self.search_entry.set_text(&uri);
+        *self.uri.borrow_mut() = uri.clone();
+        self.history.borrow_mut().push(uri);
+        let source = self.invalid_source.borrow().clone();
+        self.obj().render_source(&source);
+    }
+
     #[template_callback]
     fn on_hide_header_button_clicked(&self, _button: &Button) {
         self.leaflet.navigate(adw::NavigationDirection::Forward);
@@ -213,11 +1204,6 @@ impl Window {
         self.leaflet.navigate(adw::NavigationDirection::Back);
     }
 
-    #[template_callback]
-    fn on_show_settings_pressed(&self, _button: &Button) {
-        self.stack.set_visible_child_name("settings");
-    }
-
     #[template_callback]
     fn on_header_entry_activated(&self, row: &gtk::ListBoxRow) {
         let row_label = match row.child().and_downcast::<Label>() {
@@ -253,9 +1239,11 @@ impl Window {
 
             if let Ok(file) = dialog.open_future(None::<&gtk::Window>).await {
                 let file_path = format!("{:?}", file.path().unwrap_or_default());
-                match read_client_cert(file).await {
-                    Ok(identity) => {
-                        *window.client_cert.borrow_mut() = Some(identity);
+                let identity = read_client_cert(file).await.and_then(|identity| {
+                    Ok(network().configure(|config| config.identity = Some(identity))?)
+                });
+                match identity {
+                    Ok(()) => {
                         window.client_cert_label.set_use_underline(false);
                         window.client_cert_label.set_label(&file_path);
                     }
@@ -272,14 +1260,195 @@ impl Window {
         }));
     }
 
+    #[template_callback]
+    fn on_proxy_mode_changed(&self, _pspec: &glib::ParamSpec, dropdown: &DropDown) {
+        let mode = PROXY_MODES
+            .get(dropdown.selected() as usize)
+            .unwrap_or(&PROXY_MODES[0]);
+        self.manual_proxy_box
+            .set_sensitive(*mode == "http" || *mode == "socks5");
+
+        if let Some(settings) = self.settings.borrow().as_ref() {
+            if let Err(e) = settings.set_string("proxy-mode", mode) {
+                eprintln!("{e}");
+            }
+        }
+    }
+
     #[template_callback]
     fn on_client_cert_clear(&self, _: &Button) {
-        *self.client_cert.borrow_mut() = None;
+        if let Err(e) = network().configure(|config| config.identity = None) {
+            eprintln!("{e}");
+        }
         self.client_cert_label.set_use_underline(true);
         self.client_cert_label.set_label("Ch_oose certificate");
     }
 }
 
+/// Walks the widget tree and collects the matches in every label and text view, including the
+/// ones in collapsed dropdowns and form fields
+fn collect_find_matches(widget: &gtk::Widget, query: &str, matches: &mut Vec<FindMatch>) {
+    if !widget.is_visible() {
+        return;
+    }
+
+    let text = if let Some(label) = widget.downcast_ref::<Label>() {
+        Some(label.text())
+    } else if let Some(text_view) = widget.downcast_ref::<TextView>() {
+        let buffer = text_view.buffer();
+        Some(buffer.text(&buffer.start_iter(), &buffer.end_iter(), false))
+    } else {
+        None
+    };
+    if let Some(text) = text {
+        for range in find::find_matches(&text, query) {
+            matches.push(FindMatch {
+                widget: widget.clone(),
+                range,
+            });
+        }
+        return;
+    }
+
+    let mut child = widget.first_child();
+    while let Some(widget) = child {
+        collect_find_matches(&widget, query, matches);
+        child = widget.next_sibling();
+    }
+}
+
+fn highlight_find_match(find_match: &FindMatch, is_current: bool) {
+    if let Some(label) = find_match.widget.downcast_ref::<Label>() {
+        // Pango colors are 16 bit per channel
+        let (red, green, blue) = match is_current {
+            true => (0xffff, 0x9999, 0x0000),
+            false => (0xffff, 0xeeee, 0x5555),
+        };
+        // Highlighted on a copy, the attributes the label had are restored when the highlights
+        // are cleared
+        let attributes = label
+            .attributes()
+            .and_then(|attributes| attributes.copy())
+            .unwrap_or_else(pango::AttrList::new);
+        let mut background = pango::AttrColor::new_background(red, green, blue);
+        let mut foreground = pango::AttrColor::new_foreground(0, 0, 0);
+        for attribute in [&mut background, &mut foreground] {
+            attribute.set_start_index(find_match.range.start as u32);
+            attribute.set_end_index(find_match.range.end as u32);
+        }
+        attributes.insert(background);
+        attributes.insert(foreground);
+        label.set_attributes(Some(&attributes));
+    } else if let Some(text_view) = find_match.widget.downcast_ref::<TextView>() {
+        let buffer = text_view.buffer();
+        // The current tag is created last so that it takes priority over the match tag
+        let match_tag = find_tag(&buffer, FIND_MATCH_TAG, "#ffee55");
+        let current_tag = find_tag(&buffer, FIND_CURRENT_TAG, "#ff9900");
+        let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+        let start = buffer.iter_at_offset(find::char_offset(&text, find_match.range.start));
+        let end = buffer.iter_at_offset(find::char_offset(&text, find_match.range.end));
+        let tag = if is_current { current_tag } else { match_tag };
+        buffer.apply_tag(&tag, &start, &end);
+    }
+}
+
+/// Text blocks share a tag table while code blocks each have their own, so the tags are created
+/// on demand
+fn find_tag(buffer: &TextBuffer, name: &str, background: &str) -> TextTag {
+    let tag_table = buffer.tag_table();
+    tag_table.lookup(name).unwrap_or_else(|| {
+        let tag = TextTag::builder()
+            .name(name)
+            .background(background)
+            .foreground("black")
+            .build();
+        tag_table.add(&tag);
+        tag
+    })
+}
+
+/// A piece of a printed page
+enum PrintItem {
+    /// A line of a laid out block, y is the position of its baseline
+    Line {
+        layout: pango::Layout,
+        index: i32,
+        x: f64,
+        y: f64,
+    },
+    /// A separator line across the page
+    Rule { y: f64 },
+}
+
+/// Lays the blocks out on pages the size of the print context. Blocks are split between their
+/// lines, so a long block can continue on the next page
+fn paginate(
+    context: &gtk::PrintContext,
+    blocks: &[PrintBlock],
+    font: &pango::FontDescription,
+) -> Vec<Vec<PrintItem>> {
+    let scale = pango::SCALE as f64;
+    let em = font.size() as f64 / scale * context.dpi_y() / 72.0;
+    let mut pages = vec![];
+    let mut page = vec![];
+    let mut y = 0.0;
+
+    for block in blocks {
+        if y > 0.0 {
+            y += block.style.spacing() * em;
+        }
+        if block.style == BlockStyle::Separator {
+            if y + em > context.height() {
+                pages.push(std::mem::take(&mut page));
+                y = 0.0;
+            }
+            page.push(PrintItem::Rule { y: y + em / 2.0 });
+            y += em;
+            continue;
+        }
+
+        let x = block.style.indent() * em;
+        let layout = context.create_pango_layout();
+        layout.set_font_description(Some(font));
+        layout.set_width(((context.width() - x) * scale) as i32);
+        layout.set_wrap(pango::WrapMode::WordChar);
+        layout.set_markup(&block.markup());
+
+        let mut lines = layout.iter();
+        let mut index = 0;
+        loop {
+            let (_, extents) = lines.line_extents();
+            let height = extents.height() as f64 / scale;
+            let baseline = (lines.baseline() - extents.y()) as f64 / scale;
+            if y > 0.0 && y + height > context.height() {
+                pages.push(std::mem::take(&mut page));
+                y = 0.0;
+            }
+            page.push(PrintItem::Line {
+                layout: layout.clone(),
+                index,
+                x,
+                y: y + baseline,
+            });
+            y += height;
+            index += 1;
+            if !lines.next_line() {
+                break;
+            }
+        }
+    }
+    pages.push(page);
+    pages
+}
+
+/// The zoom levels of the sites that arent shown at the default zoom level, in percent
+fn site_zoom(settings: &Settings) -> HashMap<String, u32> {
+    settings
+        .value("site-zoom")
+        .get::<HashMap<String, u32>>()
+        .unwrap_or_default()
+}
+
 async fn read_client_cert(file: File) -> Result<Identity, Box<dyn std::error::Error>> {
     let reader = file.read_future(glib::PRIORITY_DEFAULT).await?;
     let bytes = reader
@@ -305,7 +1474,7 @@ impl ObjectImpl for Window {
 
     fn constructed(&self) {
         self.parent_constructed();
-        let settings = Settings::new("online.athn.browser.gnome");
+        let settings = profile::settings();
         settings
             .bind(
                 "language-preference",
@@ -313,7 +1482,118 @@ impl ObjectImpl for Window {
                 "text",
             )
             .build();
+        settings
+            .bind("proxy-host", &self.proxy_host_entry.get(), "text")
+            .build();
+        settings
+            .bind("proxy-port", &self.proxy_port_entry.get(), "value")
+            .build();
+        settings
+            .bind("proxy-bypass", &self.proxy_bypass_entry.get(), "text")
+            .build();
+        settings
+            .bind("connect-timeout", &self.connect_timeout_entry.get(), "value")
+            .build();
+        settings
+            .bind("read-timeout", &self.read_timeout_entry.get(), "value")
+            .build();
+        settings
+            .bind("max-document-size", &self.max_document_size_entry.get(), "value")
+            .build();
+        settings
+            .bind("retry-requests", &self.retry_requests_button.get(), "active")
+            .build();
+        settings
+            .bind("live-reload", &self.live_reload_button.get(), "active")
+            .build();
+        settings
+            .bind(
+                "live-reload-directory",
+                &self.live_reload_directory_button.get(),
+                "active",
+            )
+            .build();
+        settings
+            .bind("restore-session", &self.restore_session_button.get(), "active")
+            .build();
+
+        settings
+            .bind("body-font", &self.body_font_entry.get(), "text")
+            .build();
+        settings
+            .bind("monospace-font", &self.monospace_font_entry.get(), "text")
+            .build();
+        settings
+            .bind(
+                "max-content-width",
+                &self.max_content_width_entry.get(),
+                "value",
+            )
+            .build();
+
+        settings.connect_changed(
+            Some("search-providers"),
+            clone!(@weak self as window => move |_, _| window.rebuild_search_provider_list()),
+        );
+        settings.connect_changed(
+            None,
+            clone!(@weak self as window => move |_, key| match key {
+                "body-font" | "monospace-font" => window.update_typography(),
+                "max-content-width" => window.apply_max_content_width(),
+                _ => (),
+            }),
+        );
+        let proxy_mode = settings.string("proxy-mode");
         *self.settings.borrow_mut() = Some(settings);
+        self.rebuild_search_provider_list();
+
+        let proxy_mode = PROXY_MODES
+            .iter()
+            .position(|mode| *mode == proxy_mode.as_str())
+            .unwrap_or_default();
+        self.proxy_mode_dropdown.set_selected(proxy_mode as u32);
+
+        self.find_bar.connect_entry(&*self.find_entry);
+        // The search entry only handles plain Enter, Shift+Enter goes to the previous match
+        let find_keys = gtk::EventControllerKey::new();
+        find_keys.set_propagation_phase(gtk::PropagationPhase::Capture);
+        find_keys.connect_key_pressed(
+            clone!(@weak self as window => @default-return glib::signal::Inhibit(false), move |_, key, _, modifiers| {
+                let is_enter = matches!(key, gdk::Key::Return | gdk::Key::KP_Enter | gdk::Key::ISO_Enter);
+                if is_enter && modifiers.contains(gdk::ModifierType::SHIFT_MASK) {
+                    window.step_find_match(false);
+                    return glib::signal::Inhibit(true);
+                }
+                glib::signal::Inhibit(false)
+            }),
+        );
+        self.find_entry.add_controller(find_keys);
+
+        if self.private.get() {
+            self.obj().add_css_class("private");
+            self.obj().set_title(Some("ATHN browser (Private)"));
+        }
+
+        self.setup_actions();
+        self.apply_site_zoom();
+
+        gtk::style_context_add_provider_for_display(
+            &WidgetExt::display(&*self.obj()),
+            &self.typography_provider,
+            gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
+        );
+        self.update_typography();
+
+        // Text buffers can only be given a tag table when they are created
+        self.source_view
+            .set_buffer(Some(&TextBuffer::new(Some(&*self.source_tag_table))));
+    }
+
+    fn dispose(&self) {
+        gtk::style_context_remove_provider_for_display(
+            &WidgetExt::display(&*self.obj()),
+            &self.typography_provider,
+        );
     }
 }
 impl WidgetImpl for Window {}
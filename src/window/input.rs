@@ -1,7 +1,10 @@
-use crate::athn_document::form::ID;
+use crate::athn_document::form::{DateValue, ID};
+use gtk::glib;
+use serde::ser::{SerializeStruct, Serializer};
 use serde::Serialize;
+use std::path::PathBuf;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Input {
     #[serde(flatten)]
     pub id: ID,
@@ -9,9 +12,14 @@ pub struct Input {
     pub value: InputTypes,
     #[serde(skip)]
     pub valid: bool,
+    /// Hidden fields are left out of validation and the submitted data
+    #[serde(skip)]
+    pub hidden: bool,
 }
 
-#[derive(Debug, Serialize)]
+/// Boxed so it can be sent with the value-changed signal of the form fields
+#[derive(Debug, Clone, PartialEq, Serialize, glib::Boxed)]
+#[boxed_type(name = "AthnInputTypes")]
 #[serde(tag = "type", content = "value")]
 #[serde(rename_all = "lowercase")]
 pub enum InputTypes {
@@ -19,10 +27,106 @@ pub enum InputTypes {
     Float(Option<f64>),
     String(Option<String>),
     Bool(Option<bool>),
-    #[serde(with = "humantime_serde")]
-    Date(Option<std::time::SystemTime>),
+    /// Serialized in the format of the mode of the field
+    Date(Option<DateValue>),
     Email(Option<email_address::EmailAddress>),
-    File(Option<String>),
+    /// The base64 encoded file in JSON submissions, see [FileInput]
+    File(Option<FileInput>),
+    /// In the E.164 format
+    Tel(Option<String>),
+    /// The values of the children of every entry in the list
+    List(Vec<Vec<Input>>),
+}
+
+/// A file picked in a file field. It's only read when the form is submitted
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileInput {
+    pub name: String,
+    pub mime_type: String,
+    /// In bytes
+    pub size: u64,
+    pub path: PathBuf,
+    /// The base64 encoded file, only JSON submissions have it. Multipart submissions send the file
+    /// in a part of its own instead
+    pub content: Option<String>,
+    /// The name of the part the file is sent in, only multipart submissions have it
+    pub part: Option<String>,
+}
+
+/// JSON submissions send the base64 encoded file as the value, like they always have. Multipart
+/// submissions send the name and type of the file with the part it's in instead
+impl Serialize for FileInput {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if let Some(content) = &self.content {
+            return serializer.serialize_str(content);
+        }
+        let mut file = serializer.serialize_struct("FileInput", 3)?;
+        file.serialize_field("name", &self.name)?;
+        file.serialize_field("type", &self.mime_type)?;
+        file.serialize_field("part", &self.part)?;
+        file.end()
+    }
+}
+
+/// Every picked file in the inputs of a form, including the files in lists
+pub fn files(inputs: &[Input]) -> Vec<&FileInput> {
+    let mut found = vec![];
+    for input in inputs {
+        match &input.value {
+            InputTypes::File(picked) => found.extend(picked.iter()),
+            InputTypes::List(entries) => {
+                for entry in entries {
+                    found.extend(files(entry));
+                }
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+/// Like [files], for filling in the contents of the files
+pub fn files_mut(inputs: &mut [Input]) -> Vec<&mut FileInput> {
+    let mut found = vec![];
+    for input in inputs {
+        match &mut input.value {
+            InputTypes::File(picked) => found.extend(picked.iter_mut()),
+            InputTypes::List(entries) => {
+                for entry in entries {
+                    found.extend(files_mut(entry));
+                }
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+/// Names the part every picked file is sent in for multipart submissions. The file of a field is
+/// sent in a part named after its ID, and the files in lists have the ID of the list and the index
+/// of their entry in front, like `attachments.2.file`. IDs cant contain dots, so the names are
+/// unique within the form
+pub fn name_file_parts(inputs: &mut [Input]) {
+    name_file_parts_in(inputs, "")
+}
+
+fn name_file_parts_in(inputs: &mut [Input], prefix: &str) {
+    for input in inputs {
+        let id = input.id.id_cloned();
+        match &mut input.value {
+            InputTypes::File(picked) => {
+                if let Some(file) = picked {
+                    file.part = Some(format!("{prefix}{id}"));
+                }
+            }
+            InputTypes::List(entries) => {
+                for (entry, inputs) in entries.iter_mut().enumerate() {
+                    name_file_parts_in(inputs, &format!("{prefix}{id}.{entry}."));
+                }
+            }
+            _ => {}
+        }
+    }
 }
 
 impl PartialEq<ID> for Input {
@@ -71,6 +175,7 @@ mod tests {
             id: ID::new("not_test_id").unwrap(),
             value: InputTypes::Int(None),
             valid: true,
+            hidden: false,
         };
 
         assert_ne!(input, id);
@@ -84,8 +189,102 @@ mod tests {
             id: ID::new("test_id").unwrap(),
             value: InputTypes::Int(None),
             valid: true,
+            hidden: false,
         };
 
         assert_eq!(input, id);
     }
+
+    #[test]
+    fn undecided_bool_is_null() {
+        let input = Input {
+            id: ID::new("subscribe").unwrap(),
+            value: InputTypes::Bool(None),
+            valid: true,
+            hidden: false,
+        };
+
+        assert_eq!(
+            serde_json::to_string(&input).unwrap(),
+            r#"{"id":"subscribe","type":"bool","value":null}"#
+        );
+    }
+
+    fn file_input(id: &str, file: Option<FileInput>) -> Input {
+        Input {
+            id: ID::new(id).unwrap(),
+            value: InputTypes::File(file),
+            valid: true,
+            hidden: false,
+        }
+    }
+
+    fn picked(name: &str) -> FileInput {
+        FileInput {
+            name: name.to_string(),
+            mime_type: "text/plain".to_string(),
+            size: 5,
+            path: PathBuf::from("/tmp").join(name),
+            content: None,
+            part: None,
+        }
+    }
+
+    #[test]
+    fn file_content_only_when_read() {
+        let mut input = file_input("notes", Some(picked("a.txt")));
+
+        assert_eq!(
+            serde_json::to_string(&file_input("notes", None)).unwrap(),
+            r#"{"id":"notes","type":"file","value":null}"#
+        );
+
+        files_mut(std::slice::from_mut(&mut input))[0].content = Some("aGVsbG8=".to_string());
+        assert_eq!(
+            serde_json::to_string(&input).unwrap(),
+            r#"{"id":"notes","type":"file","value":"aGVsbG8="}"#
+        );
+    }
+
+    #[test]
+    fn files_in_lists() {
+        let list = Input {
+            id: ID::new("attachments").unwrap(),
+            value: InputTypes::List(vec![
+                vec![file_input("attachment", Some(picked("a.txt")))],
+                vec![file_input("attachment", None)],
+                vec![file_input("attachment", Some(picked("b.txt")))],
+            ]),
+            valid: true,
+            hidden: false,
+        };
+        let mut inputs = vec![file_input("cover", Some(picked("cover.txt"))), list];
+
+        name_file_parts(&mut inputs);
+
+        let parts: Vec<(&str, &str)> = files(&inputs)
+            .into_iter()
+            .map(|file| (file.part.as_deref().unwrap(), file.name.as_str()))
+            .collect();
+        assert_eq!(
+            parts,
+            [
+                ("cover", "cover.txt"),
+                ("attachments.0.attachment", "a.txt"),
+                ("attachments.2.attachment", "b.txt"),
+            ]
+        );
+    }
+
+    #[test]
+    fn file_part_is_serialized() {
+        let mut inputs = vec![file_input("notes", Some(picked("a.txt")))];
+
+        name_file_parts(&mut inputs);
+
+        assert_eq!(
+            serde_json::to_string(&inputs[0]).unwrap(),
+            r#"{"id":"notes","type":"file","value":{"name":"a.txt","type":"text/plain","part":"notes"}}"#
+        );
+    }
 }
@@ -0,0 +1,150 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+#[derive(Debug)]
+pub enum FileError {
+    InvalidPath,
+    Io(std::io::Error),
+    NotUtf8(PathBuf),
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileError::InvalidPath => write!(f, "The URL does not point to a local file"),
+            FileError::Io(e) => write!(f, "{e}"),
+            FileError::NotUtf8(path) => write!(
+                f,
+                "{} is not a text file, ATHN documents have to be encoded as UTF-8",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl From<std::io::Error> for FileError {
+    fn from(e: std::io::Error) -> Self {
+        FileError::Io(e)
+    }
+}
+
+/// Reads a local document, or generates a listing if the URL points to a directory
+pub fn get_document_by_file(url: &Url) -> Result<String, FileError> {
+    // to_file_path takes care of percent decoding, so files with spaces in their names work
+    let path = url.to_file_path().map_err(|_| FileError::InvalidPath)?;
+
+    if path.is_dir() {
+        return directory_listing(&path);
+    }
+
+    let bytes = fs::read(&path)?;
+    String::from_utf8(bytes).map_err(|_| FileError::NotUtf8(path))
+}
+
+/// An entry in a generated directory listing
+#[derive(PartialEq, Debug)]
+struct ListingEntry {
+    name: String,
+    url: Url,
+    is_dir: bool,
+}
+
+fn directory_listing(path: &Path) -> Result<String, FileError> {
+    let mut entries = vec![];
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let entry_path = entry.path();
+        let is_dir = entry_path.is_dir();
+        let is_document = entry_path.extension().is_some_and(|e| e == "athn");
+
+        if name.starts_with('.') || !(is_dir || is_document) {
+            continue;
+        }
+        if let Ok(url) = Url::from_file_path(&entry_path) {
+            entries.push(ListingEntry { name, url, is_dir });
+        }
+    }
+
+    let parent = path.parent().and_then(|p| Url::from_file_path(p).ok());
+    Ok(format_listing(&path.display().to_string(), parent, entries))
+}
+
+/// Turns the contents of a directory into an ATHN document, subdirectories are listed first
+fn format_listing(title: &str, parent: Option<Url>, mut entries: Vec<ListingEntry>) -> String {
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    // Line breaks in a file name would end the line early
+    let clean = |s: &str| s.replace(['\n', '\r'], " ");
+
+    let mut document = format!("TM Index of {}\n+++\n", clean(title));
+    if let Some(parent) = parent {
+        document.push_str(&format!("@@@{parent} | Parent directory\n"));
+    }
+    if entries.is_empty() {
+        document.push_str("This directory does not contain any ATHN documents\n");
+    }
+    for entry in entries {
+        let slash = if entry.is_dir { "/" } else { "" };
+        document.push_str(&format!("@@@{} | {}{slash}\n", entry.url, clean(&entry.name)));
+    }
+    document
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::athn_document::{self, line_types::*, Document, ParserState};
+
+    #[test]
+    fn listing_is_valid_document() {
+        let entries = vec![
+            ListingEntry {
+                name: "b file.athn".to_string(),
+                url: Url::parse("file:///docs/b%20file.athn").unwrap(),
+                is_dir: false,
+            },
+            ListingEntry {
+                name: "subdir".to_string(),
+                url: Url::parse("file:///docs/subdir").unwrap(),
+                is_dir: true,
+            },
+        ];
+        let listing = format_listing("/docs", Url::parse("file:///").ok(), entries);
+
+        let document =
+            athn_document::parse(listing.lines(), Document::builder(), ParserState::default())
+                .unwrap()
+                .build();
+
+        assert_eq!(document.metadata.title, "Index of /docs");
+        assert_eq!(
+            document.main,
+            vec![
+                MainLine::LinkLine(Link {
+                    url: "file:///".to_string(),
+                    label: Some("Parent directory".to_string()),
+                }),
+                MainLine::LinkLine(Link {
+                    url: "file:///docs/subdir".to_string(),
+                    label: Some("subdir/".to_string()),
+                }),
+                MainLine::LinkLine(Link {
+                    url: "file:///docs/b%20file.athn".to_string(),
+                    label: Some("b file.athn".to_string()),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_listing() {
+        let listing = format_listing("/", None, vec![]);
+
+        assert_eq!(
+            listing,
+            "TM Index of /\n+++\nThis directory does not contain any ATHN documents\n"
+        );
+    }
+}
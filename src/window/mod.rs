@@ -1,32 +1,31 @@
+mod about;
+mod find;
 mod imp;
-mod input;
+pub mod input;
+mod local;
+mod print;
+mod search;
+mod source;
+mod typography;
 
 use crate::athn_document::form;
 use crate::athn_document::{line_types, line_types::MainLine, Document, Metadata};
+use crate::session::WindowState;
 use adw::prelude::*;
 use adw::subclass::prelude::*;
 use adw::{ActionRow, Application, ButtonContent, ExpanderRow};
-use base64::{engine::general_purpose, Engine as _};
-use email_address::EmailAddress;
-use gio::File;
-use glib::{clone, closure_local, source::PRIORITY_DEFAULT, GString, Object};
+use glib::{clone, closure_local, GString, Object};
 use gtk::{
-    gio, glib, CheckButton, Label, ListBox, ListBoxRow, Orientation::Horizontal, Separator,
-    TextBuffer, TextIter, TextTagTable, TextView,
+    gio, glib, graphene, Label, ListBox, ListBoxRow, Orientation::Horizontal, ScrolledWindow,
+    Separator, TextBuffer, TextIter, TextTagTable, TextView,
 };
+pub use imp::document_text;
 use input::*;
 use serde::Deserialize;
-use std::str::FromStr;
-use std::time::{Duration, SystemTime};
 use url::Url;
 // Custom widgets
-use crate::date::DateFormField;
-use crate::email::EmailFormField;
-use crate::enum_field::EnumFormField;
-use crate::file::FileFormField;
-use crate::float::FloatFormField;
-use crate::integer::IntFormField;
-use crate::string::StringFormField;
+use crate::form_field::FormField;
+use crate::list::ListFormField;
 use crate::submit::SubmitFormField;
 
 glib::wrapper! {
@@ -41,12 +40,24 @@ impl Window {
         Object::builder().property("application", app).build()
     }
 
+    /// A window that isnt saved in the session
+    pub fn new_private(app: &Application) -> Self {
+        Object::builder()
+            .property("application", app)
+            .property("private", true)
+            .build()
+    }
+
     pub fn render(&self, document: Document, base_url: &Url) {
         clear_list_box(&self.imp().canvas);
         clear_list_box(&self.imp().header);
+        self.imp()
+            .print_blocks
+            .replace(print::print_blocks(&document, base_url));
 
         self.render_metadata(document.metadata);
 
+        *self.imp().form_fields.borrow_mut() = form_fields(&document.main);
         for line in document.main {
             self.render_main_line(line, base_url);
         }
@@ -64,6 +75,54 @@ impl Window {
         }
 
         list_box_map(&self.imp().canvas, |row, _| row.set_activatable(false));
+        self.update_conditions();
+
+        // The old matches point to widgets that arent on the canvas anymore
+        self.imp().find_in_page();
+        self.imp().apply_site_zoom();
+    }
+
+    /// Shows the raw text of a document with every line highlighted by its kind. Jumps to the line
+    /// the parser fails on if there is one
+    pub fn render_source(&self, source: &str) {
+        let imp = self.imp();
+        let buffer = imp.source_view.buffer();
+        buffer.set_text(source);
+        imp.print_blocks.replace(print::source_blocks(source));
+
+        for (line, kind) in source::classify_lines(source).into_iter().enumerate() {
+            let Some(tag) = kind.tag_name() else { continue };
+            let Some(start) = buffer.iter_at_line(line as i32) else {
+                continue;
+            };
+            let mut end = start.clone();
+            if !end.ends_line() {
+                end.forward_to_line_end();
+            }
+            buffer.apply_tag_by_name(tag, &start, &end);
+        }
+        imp.source_line_numbers
+            .set_label(&source::line_numbers(buffer.line_count() as usize));
+        imp.stack.set_visible_child_name("source");
+        imp.apply_site_zoom();
+
+        let error_line =
+            source::error_line(source).and_then(|line| buffer.iter_at_line(line as i32));
+        let Some(start) = error_line else {
+            imp.source_window.vadjustment().set_value(0.0);
+            return;
+        };
+        let mut end = start.clone();
+        end.forward_line();
+        buffer.apply_tag_by_name("source-error-line", &start, &end);
+        buffer.place_cursor(&start);
+
+        // The source hasnt been laid out yet
+        glib::idle_add_local_once(clone!(@weak self as window => move || {
+            let imp = window.imp();
+            let y = text_iter_y(&imp.source_view, &start);
+            scroll_to_widget(&imp.source_window, imp.source_view.upcast_ref(), y);
+        }));
     }
 
     fn render_footer_section(&self, footer: Vec<line_types::FooterLine>, base_url: &Url) {
@@ -145,35 +204,206 @@ impl Window {
     }
 
     fn render_form_field(&self, field: form::FormField, base_url: &Url, form_idx: usize) {
-        use form::FormField::*;
-        macro_rules! append {
-            ($x:expr) => {
-                self.imp().canvas.append(&$x)
-            };
-        }
         let mut form_data = self.imp().form_data.borrow_mut();
         if form_data.is_empty() {
             form_data.push(vec![]);
         }
         std::mem::drop(form_data);
-        match field {
-            Submit(id, field) => append!(create_submit_form_field(
-                self, form_idx, id, field, base_url
-            )),
-            Integer(id, field) => append!(create_int_form_field(self, form_idx, id, field)),
-            Float(id, field) => append!(create_float_form_field(self, form_idx, id, field)),
-            String(id, field) if field.variant.is_some() => {
-                append!(create_enum_form_field(self, form_idx, id, field))
+        if let form::FormField::Submit(id, field) = field {
+            let widget = create_submit_form_field(self, form_idx, id, field, base_url);
+            return self.imp().canvas.append(&widget);
+        }
+        let all_fields = self.imp().form_fields.borrow();
+        let fields = all_fields
+            .get(form_idx)
+            .map_or(&[][..], |fields| fields.as_slice());
+        if form::is_list_child(field.id(), fields) {
+            return;
+        }
+        let Some(widget) = FormField::new(form_idx, field, fields) else {
+            return;
+        };
+        std::mem::drop(all_fields);
+
+        if let Some(input) = widget.input() {
+            self.imp().form_data.borrow_mut()[form_idx].push(input);
+        }
+        widget.connect_value_changed(clone!(@weak self as window => move |field, value| {
+            let Ok(id) = form::ID::new(&field.id()) else {
+                return;
+            };
+            let mut all_data = window.imp().form_data.borrow_mut();
+            if let Some(data) = all_data.get_mut(field.form_idx() as usize) {
+                let _ = override_element_by_id(data, id, value.clone(), field.submittable());
+            }
+            std::mem::drop(all_data);
+            if let InputTypes::Bool(_) = value {
+                window.update_conditions();
+            }
+        }));
+        self.imp().canvas.append(&widget);
+    }
+
+    /// Puts previously entered form data back into the form fields after a rerender. Fields are
+    /// matched by form and ID, and only get a value back if their type is the same as before
+    pub fn restore_form_data(&self, saved: Vec<Vec<Input>>) {
+        let mut row = self.imp().canvas.first_child();
+        while let Some(current_row) = row {
+            if let Some(widget) = current_row
+                .downcast_ref::<ListBoxRow>()
+                .and_then(|row| row.child())
+            {
+                if let Some(field) = widget.downcast_ref::<FormField>() {
+                    if let Some(value) = saved_value(&saved, field.form_idx(), &field.id()) {
+                        field.set_input(value);
+                    }
+                }
+            }
+            row = current_row.next_sibling();
+        }
+    }
+
+    /// Shows or hides every conditional form field depending on the bool fields it targets. Hidden
+    /// fields are marked as such in the form data so they dont block or get sent with a submit
+    fn update_conditions(&self) {
+        let all_fields = self.imp().form_fields.borrow();
+        let mut row = self.imp().canvas.first_child();
+        while let Some(current_row) = row {
+            row = current_row.next_sibling();
+            let Some(field) = current_row
+                .downcast_ref::<ListBoxRow>()
+                .and_then(|row| row.child())
+                .and_then(|widget| widget.downcast::<FormField>().ok())
+            else {
+                continue;
+            };
+            let form_idx = field.form_idx() as usize;
+            let Some(fields) = all_fields.get(form_idx) else {
+                continue;
+            };
+            let Some(declaration) = fields.iter().find(|f| f.id().id_cloned() == field.id()) else {
+                continue;
+            };
+
+            let all_data = self.imp().form_data.borrow();
+            let checked = |id: &form::ID| {
+                matches!(
+                    saved_value(&all_data, form_idx as u64, &id.id_cloned()),
+                    Some(InputTypes::Bool(Some(true)))
+                )
+            };
+            // A field with a broken condition is shown so the error can be seen, it cant be
+            // submitted
+            let (shown, error) = match form::is_shown(declaration, fields, &checked) {
+                Ok(shown) => (shown, String::new()),
+                Err(error) => (true, error),
+            };
+            std::mem::drop(all_data);
+            field.set_condition_error(error);
+
+            current_row.set_visible(shown);
+            let mut all_data = self.imp().form_data.borrow_mut();
+            if let Some(input) = all_data
+                .get_mut(form_idx)
+                .and_then(|data| data.iter_mut().find(|input| input.id == *declaration.id()))
+            {
+                input.hidden = !shown;
+                input.valid = field.submittable();
             }
-            String(id, field) => append!(create_string_form_field(self, form_idx, id, field)),
-            Boolean(id, field) => append!(create_bool_form_field(self, form_idx, id, field)),
-            Date(id, field) => append!(create_date_form_field(self, form_idx, id, field)),
-            Email(id, field) => append!(create_email_form_field(self, form_idx, id, field)),
-            File(id, field) => append!(create_file_form_field(self, form_idx, id, field)),
-            _ => (),
         }
     }
 
+    /// Shows the errors the server gave for a submitted form under the fields they're about, and
+    /// clears the errors of the other fields. The first field with an error is focused. Returns
+    /// the errors that aren't about any field on the page
+    fn show_server_errors<'a>(
+        &self,
+        form_idx: usize,
+        errors: &'a [FormValidation],
+    ) -> Vec<&'a FormValidation> {
+        let mut matched = vec![false; errors.len()];
+        let mut first_failing: Option<FormField> = None;
+        let mut row = self.imp().canvas.first_child();
+        while let Some(current_row) = row {
+            row = current_row.next_sibling();
+            let Some(field) = current_row
+                .downcast_ref::<ListBoxRow>()
+                .and_then(|row| row.child())
+                .and_then(|widget| widget.downcast::<FormField>().ok())
+            else {
+                continue;
+            };
+            if field.form_idx() as usize != form_idx {
+                continue;
+            }
+
+            // The idx of an error picks the entry for the children of lists
+            let mut targets = vec![(None, field.clone())];
+            if let Some(list) = field.downcast_ref::<ListFormField>() {
+                for (idx, entry) in list.entry_fields().into_iter().enumerate() {
+                    targets.extend(entry.into_iter().map(|child| (Some(idx), child)));
+                }
+            }
+            for (idx, target) in targets {
+                let id = target.id();
+                let error = errors
+                    .iter()
+                    .enumerate()
+                    .find(|(_, error)| error.id == id && (idx.is_none() || error.idx == idx));
+                let Some((error_idx, error)) = error else {
+                    target.set_server_error(String::new());
+                    continue;
+                };
+                matched[error_idx] = true;
+                target.set_server_error(error.message.clone());
+                first_failing.get_or_insert(target);
+            }
+        }
+
+        if let Some(field) = first_failing {
+            field.grab_focus();
+            scroll_to_widget(&self.imp().canvas_window, field.upcast_ref(), 0.0);
+        }
+        errors
+            .iter()
+            .zip(matched)
+            .filter(|(_, matched)| !matched)
+            .map(|(error, _)| error)
+            .collect()
+    }
+
+    /// The page, navigation history and scroll position of the window, for saving the session
+    pub fn session_state(&self) -> WindowState {
+        let imp = self.imp();
+        let uri = imp.uri.borrow().clone();
+        let scrolled_window = match uri.starts_with(source::VIEW_SOURCE_PREFIX) {
+            true => &imp.source_window,
+            false => &imp.canvas_window,
+        };
+        WindowState {
+            back_stack: imp.back_stack.borrow().clone(),
+            forward_stack: imp.forward_stack.borrow().clone(),
+            scroll: scrolled_window.vadjustment().value(),
+            uri,
+        }
+    }
+
+    /// Opens the page of a window from a saved session and puts its history and scroll position
+    /// back
+    pub fn restore_session_state(&self, state: WindowState) {
+        let imp = self.imp();
+        *imp.back_stack.borrow_mut() = state.back_stack;
+        *imp.forward_stack.borrow_mut() = state.forward_stack;
+        imp.update_navigation_actions();
+        if state.uri.is_empty() {
+            return;
+        }
+
+        imp.navigating_history.set(true);
+        imp.open_uri(state.uri, Some(state.scroll));
+        imp.navigating_history.set(false);
+    }
+
     fn render_metadata(&self, metadata: Metadata) {
         self.imp()
             .canvas
@@ -277,305 +507,28 @@ fn append_text_to_block(text_view: &TextView, content: String) {
     buffer.insert_at_cursor(format!("\n{}", content).as_str());
 }
 
-fn create_int_form_field(
-    window: &Window,
-    form_idx: usize,
-    id: form::ID,
-    field: form::IntField,
-) -> IntFormField {
-    let default = field.global.default.unwrap_or(0);
-
-    let widget = IntFormField::new(form_idx, id.clone(), field);
-
-    let new_input_data = Input {
-        id,
-        value: InputTypes::Int(Some(default)),
-        valid: true,
-    };
-    window.imp().form_data.borrow_mut()[form_idx].push(new_input_data);
-
-    #[allow(unused_must_use)]
-    widget.connect_closure(
-        "updated",
-        false,
-        closure_local!(@watch window => move |_: &IntFormField, form_idx: u64, id: String, value: i32, valid: bool| {
-            let id = form::ID::new(&id).unwrap();
-            let mut all_data = window.imp().form_data.borrow_mut();
-            override_element_by_id(&mut all_data[form_idx as usize], id, InputTypes::Int(Some(value.into())), valid);
-        }),
-    );
-
-    widget
-}
-
-fn create_float_form_field(
-    window: &Window,
-    form_idx: usize,
-    id: form::ID,
-    field: form::FloatField,
-) -> FloatFormField {
-    let default = field.global.default.unwrap_or(0.001);
-
-    let widget = FloatFormField::new(form_idx, id.clone(), field);
-
-    let new_input_data = Input {
-        id,
-        value: InputTypes::Float(Some(default)),
-        valid: true,
-    };
-    window.imp().form_data.borrow_mut()[form_idx].push(new_input_data);
-
-    #[allow(unused_must_use)]
-    widget.connect_closure(
-        "updated",
-        false,
-        closure_local!(@watch window => move |_: &FloatFormField, form_idx: u64, id: String, value: f64, valid: bool| {
-            let id = form::ID::new(&id).unwrap();
-            let mut all_data = window.imp().form_data.borrow_mut();
-            override_element_by_id(&mut all_data[form_idx as usize], id, InputTypes::Float(Some(value)), valid);
-        }),
-    );
-
-    widget
-}
-
-fn create_string_form_field(
-    window: &Window,
-    form_idx: usize,
-    id: form::ID,
-    field: form::StringField,
-) -> StringFormField {
-    let default = field.global.default.clone();
-    let widget = StringFormField::new(form_idx, id.clone(), field);
-
-    let valid = widget
-        .imp()
-        .is_input_valid(&default.clone().unwrap_or_default());
-
-    let new_input_data = Input {
-        id,
-        value: InputTypes::String(default),
-        valid,
-    };
-    window.imp().form_data.borrow_mut()[form_idx].push(new_input_data);
-
-    #[allow(unused_must_use)]
-    widget.connect_closure(
-        "updated",
-        false,
-        closure_local!(@watch window => move |_form_field: &StringFormField, form_idx: u64, id: String, input: String, valid: bool| {
-            let id = form::ID::new(&id).unwrap();
-            let mut all_data = window.imp().form_data.borrow_mut();
-            override_element_by_id(&mut all_data[form_idx as usize], id, InputTypes::String(Some(input)), valid);
-        }),
-    );
-
-    widget
-}
-
-fn create_enum_form_field(
-    window: &Window,
-    form_idx: usize,
-    id: form::ID,
-    field: form::StringField,
-) -> EnumFormField {
-    let widget = EnumFormField::new(form_idx, id.clone(), field);
-    let default = widget.imp().entry.selected_item();
-    let default = default.map(|v| crate::enum_field::extract_string_from_object(&v));
-
-    let new_input_data = Input {
-        id,
-        value: InputTypes::String(default),
-        valid: true,
-    };
-    window.imp().form_data.borrow_mut()[form_idx].push(new_input_data);
-
-    #[allow(unused_must_use)]
-    widget.connect_closure(
-        "updated",
-        false,
-        closure_local!(@watch window => move |_form_field: &EnumFormField, form_idx: u64, id: String, input: Option<String>, valid: bool| {
-            let id = form::ID::new(&id).unwrap();
-            let mut all_data = window.imp().form_data.borrow_mut();
-            override_element_by_id(&mut all_data[form_idx as usize], id, InputTypes::String(input), valid);
-        }),
-    );
-
-    widget
-}
-
-fn create_bool_form_field(
-    window: &Window,
-    form_idx: usize,
-    id: form::ID,
-    field: form::BoolField,
-) -> CheckButton {
-    let label = field.global.label.unwrap_or(id.id_cloned());
-    let optional = field.global.optional;
-    let default = match (field.global.default, optional) {
-        (None, true) => None,
-        (None, false) => Some(false),
-        (Some(v), _) => Some(v),
-    };
-
-    let widget = CheckButton::with_label(&label);
-    widget.set_tooltip_text(Some(&id.id_cloned()));
-    widget.set_has_tooltip(false);
-    widget.set_widget_name(&form_idx.to_string());
-    match default {
-        Some(default) => widget.set_active(default),
-        None => widget.set_inconsistent(true),
-    }
-
-    let new_input_data = Input {
-        id,
-        value: InputTypes::Bool(default),
-        valid: true,
-    };
-    window.imp().form_data.borrow_mut()[form_idx].push(new_input_data);
-
-    #[allow(unused_must_use)]
-    widget.connect_closure(
-        "toggled",
-        false,
-        closure_local!(@watch window => move |button: &CheckButton| {
-            let id = form::ID::new(button.tooltip_text().unwrap().as_str()).unwrap();
-            let mut all_data = window.imp().form_data.borrow_mut();
-            let form_idx: usize = button.widget_name().parse().unwrap_or_default();
-            button.set_inconsistent(false);
-            override_element_by_id(&mut all_data[form_idx], id, InputTypes::Bool(Some(button.is_active())), true);
-        }),
-    );
-
-    widget
-}
-
-fn create_date_form_field(
-    window: &Window,
-    form_idx: usize,
-    id: form::ID,
-    field: form::DateField,
-) -> DateFormField {
-    let default = field.global.default;
-
-    let widget = DateFormField::new(form_idx, id.clone(), field);
-
-    let new_input_data = Input {
-        id,
-        value: InputTypes::Date(default),
-        valid: true,
-    };
-    window.imp().form_data.borrow_mut()[form_idx].push(new_input_data);
-
-    #[allow(unused_must_use)]
-    widget.connect_closure(
-        "updated",
-        false,
-        closure_local!(@watch window => move |_form_field: &DateFormField, form_idx: u64, id: String, time: glib::DateTime| {
-            let id = form::ID::new(&id).unwrap();
-            let mut all_data = window.imp().form_data.borrow_mut();
-            let time_formatted = SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(time.to_unix() as u64));
-            override_element_by_id(&mut all_data[form_idx as usize], id, InputTypes::Date(time_formatted), true);
-        }),
-    );
-
-    widget
-}
-
-fn create_email_form_field(
-    window: &Window,
-    form_idx: usize,
-    id: form::ID,
-    field: form::EmailField,
-) -> EmailFormField {
-    let default = field.global.default.clone();
-    let valid = if default.is_none() && field.global.optional == false {
-        false
-    } else {
-        true
-    };
-    let widget = EmailFormField::new(form_idx, id.clone(), field);
-
-    let new_input_data = Input {
-        id,
-        value: InputTypes::Email(default),
-        valid,
-    };
-    window.imp().form_data.borrow_mut()[form_idx].push(new_input_data);
-
-    #[allow(unused_must_use)]
-    widget.connect_closure(
-        "updated",
-        false,
-        closure_local!(@watch window => move |_form_field: &EmailFormField, form_idx: u64, id: String, email: String, valid: bool| {
-            let id = form::ID::new(&id).unwrap();
-            let mut all_data = window.imp().form_data.borrow_mut();
-            let email_formatted = EmailAddress::from_str(&email).ok();
-            override_element_by_id(&mut all_data[form_idx as usize], id, InputTypes::Email(email_formatted), valid);
-        }),
-    );
-
-    widget
-}
-
-fn create_file_form_field(
-    window: &Window,
-    form_idx: usize,
-    id: form::ID,
-    field: form::FileField,
-) -> FileFormField {
-    let optional = field.global.optional;
-    let widget = FileFormField::new(form_idx, id.clone(), field);
-
-    let new_input_data = Input {
-        id,
-        value: InputTypes::File(None),
-        valid: optional,
-    };
-    window.imp().form_data.borrow_mut()[form_idx].push(new_input_data);
-
-    #[allow(unused_must_use)]
-    widget.connect_closure(
-        "updated",
-        false,
-        closure_local!(@watch window => move |_form_field: &FileFormField, form_idx: u64, id: String, file: File, valid: bool| {
-            let ctx = glib::MainContext::default();
-            ctx.spawn_local(clone!(@weak file, @strong id, @weak window, @strong valid => async move {
-                let id = form::ID::new(&id).unwrap();
-                let mut all_data = window.imp().form_data.borrow_mut();
-                let encoded = match base64_encode_file(file).await {
-                    Ok(v) => Some(v),
-                    Err(_) => None,
-                };
-                override_element_by_id(&mut all_data[form_idx as usize], id, InputTypes::File(encoded), valid);
-            }));
-        }),
-    );
-
-    widget.connect_closure(
-        "too-big-file-selected",
-        false,
-        closure_local!(@watch window => move |field: FileFormField| {
-            let message = format!("File selected is too big, max size allowed is: {}B", field.max_file_size());
-            eprintln!("{message}");
-
-            let toast = adw::Toast::new(message.as_str());
-            window.imp().toaster.add_toast(toast);
-            if let Some(toast_widget) = window.imp().toaster.last_child() {
-                toast_widget.add_css_class("error");
+/// The fields of every form in the document, lists need them to find their children
+fn form_fields(main: &[MainLine]) -> Vec<Vec<form::FormField>> {
+    let mut forms: Vec<Vec<form::FormField>> = vec![];
+    for line in main {
+        if let MainLine::FormFieldLine(form_count, field) = line {
+            let form_idx = form_count - 1;
+            if forms.len() <= form_idx {
+                forms.resize(form_idx + 1, vec![]);
             }
-        }),
-    );
-
-    widget
+            forms[form_idx].push(field.clone());
+        }
+    }
+    forms
 }
 
-async fn base64_encode_file(file: File) -> Result<String, glib::Error> {
-    let reader = file.read_future(PRIORITY_DEFAULT).await?;
-    let bytes = reader
-        .read_bytes_future(std::i32::MAX as usize, PRIORITY_DEFAULT)
-        .await?;
-    Ok(general_purpose::STANDARD.encode(bytes))
+fn saved_value<'a>(saved: &'a [Vec<Input>], form_idx: u64, id: &str) -> Option<&'a InputTypes> {
+    let id = form::ID::new(id).ok()?;
+    saved
+        .get(form_idx as usize)?
+        .iter()
+        .find(|input| **input == id)
+        .map(|input| &input.value)
 }
 
 /// Returns an error if the id doesnt exist
@@ -599,24 +552,8 @@ fn create_submit_form_field(
     base_url: &Url,
 ) -> SubmitFormField {
     let url = validate_url(&field.destination, base_url).ok();
-    let language_string = window
-        .imp()
-        .settings
-        .borrow()
-        .clone()
-        .map(|settings| settings.string("language-preference"))
-        .unwrap_or_default();
-    let identity = window.imp().client_cert.borrow().clone();
-
-    let widget = SubmitFormField::new(
-        form_idx,
-        id,
-        field.label,
-        url,
-        field.redirect,
-        language_string.to_string(),
-        identity,
-    );
+
+    let widget = SubmitFormField::new(form_idx, id, field.label, url, field.redirect);
 
     widget.connect_closure(
         "data-request",
@@ -624,7 +561,9 @@ fn create_submit_form_field(
         closure_local!(@watch window => move |button: SubmitFormField| {
             let form_idx = button.form_idx() as usize;
             button.set_invalid_form(!window.imp().is_form_valid(form_idx));
-            button.set_serialized_data(serde_json::to_string(&window.imp().form_data.borrow()[form_idx]).unwrap());
+            let data = window.imp().form_data.borrow();
+            let shown = data[form_idx].iter().filter(|input| !input.hidden).cloned().collect();
+            button.set_inputs(shown);
         }),
     );
 
@@ -656,28 +595,31 @@ fn create_submit_form_field(
     );
 
     widget.connect_closure(
-        "server_validation-error",
+        "server-validation-error",
         false,
-        closure_local!(@watch window => move |_button: SubmitFormField, validation_error: std::string::String| {
-            let message = "The server responded with the following error";
-            eprintln!("{message}:");
+        closure_local!(@watch window => move |button: SubmitFormField, validation_error: std::string::String| {
+            eprintln!("The server responded with the following error:");
             eprintln!("{validation_error}");
 
-            let toast = adw::Toast::new(message);
+            let errors: serde_json::Result<Vec<FormValidation>> = serde_json::from_str(&validation_error);
+            let message = match errors {
+                Ok(errors) => {
+                    let unmatched = window.show_server_errors(button.form_idx() as usize, &errors);
+                    let mut message = "The server didnt accept some of the fields".to_string();
+                    for error in unmatched {
+                        message.push_str(&format!("\n{}: {}", error.id, error.message));
+                    }
+                    message
+                }
+                Err(_) => "The browser was unable to understand the error message from the server".to_string(),
+            };
+
+            let toast = adw::Toast::new(&message);
             toast.set_timeout(0);
             window.imp().toaster.add_toast(toast);
             if let Some(toast_widget) = window.imp().toaster.last_child() {
                 toast_widget.add_css_class("error");
             }
-
-            window.imp().server_error_window.set_visible(true);
-            let buffer = &window.imp().server_error_buffer;
-
-            let errors: serde_json::Result<Vec<FormValidation>> = serde_json::from_str(&validation_error);
-            buffer.set_text(&match errors {
-                Ok(errors) => errors.iter().map(|error| format!("Field {}: {}\n", error.id, error.message)).collect(),
-                Err(_) => "The browser was unable to understand the error message from the server".to_string(),
-            });
         }),
     );
 
@@ -690,10 +632,36 @@ fn create_submit_form_field(
 struct FormValidation {
     pub id: String,
     pub message: String,
-    #[allow(dead_code)]
+    /// Which entry of its list the field is in, for fields that are children of a list
     pub idx: Option<usize>,
 }
 
+/// Scrolls so that the point at y in the widget ends up a third of the way down the scrolled
+/// window instead of at the very top
+fn scroll_to_widget(scrolled_window: &ScrolledWindow, widget: &gtk::Widget, y: f64) {
+    // The child of the scrolled window is a viewport, the coordinates of the content inside it are
+    // the same as the coordinates of the adjustment
+    let Some(content) = scrolled_window
+        .child()
+        .and_then(|viewport| viewport.first_child())
+    else {
+        return;
+    };
+    let Some(point) = widget.compute_point(&content, &graphene::Point::new(0.0, y as f32)) else {
+        return;
+    };
+    let adjustment = scrolled_window.vadjustment();
+    adjustment.set_value(point.y() as f64 - adjustment.page_size() / 3.0);
+}
+
+/// The position of the line the iter is on, in the coordinates of the text view
+fn text_iter_y(text_view: &TextView, iter: &TextIter) -> f64 {
+    let location = text_view.iter_location(iter);
+    let (_, y) =
+        text_view.buffer_to_window_coords(gtk::TextWindowType::Widget, location.x(), location.y());
+    y as f64
+}
+
 fn list_box_map(list_box: &ListBox, map: fn(widget: &ListBoxRow, parent: &ListBox)) {
     let original_selection_mode = list_box.selection_mode();
 
@@ -719,50 +687,8 @@ fn create_document_title(title: impl Into<GString>) -> Label {
     widget
 }
 
-fn format_author_string(author_list: Option<Vec<String>>) -> Option<String> {
-    let author_list = author_list?;
-    Some(format!(
-        "By: {}",
-        author_list.iter().fold(String::new(), |acc, val| {
-            if acc.is_empty() {
-                val.to_owned()
-            } else {
-                format!("{acc}, {val}")
-            }
-        })
-    ))
-}
-
-fn format_license_string(license_list: Option<Vec<String>>) -> Option<String> {
-    let license_list = license_list?;
-    Some(format!(
-        "License{}: {}",
-        if license_list.len() > 1 { "s" } else { "" },
-        license_list.iter().fold(String::new(), |acc, val| {
-            if acc.is_empty() {
-                val.to_owned()
-            } else {
-                format!("{acc}, {val}")
-            }
-        })
-    ))
-}
-
 fn create_metaline(author: Option<Vec<String>>, license: Option<Vec<String>>) -> Option<Label> {
-    if author.is_none() && license.is_none() {
-        return None;
-    };
-
-    let author_string = format_author_string(author);
-    let license_string = format_license_string(license);
-    let both_are_some = license_string.is_some() && author_string.is_some();
-
-    let label = format!(
-        "{}{}{}",
-        author_string.unwrap_or_default(),
-        if both_are_some { ". " } else { "" },
-        license_string.unwrap_or_default()
-    );
+    let label = print::metaline_text(author.as_deref(), license.as_deref())?;
 
     Some(
         Label::builder()
@@ -844,48 +770,46 @@ fn create_code_block(content: String) -> TextView {
     widget.add_css_class("monospace");
     // This is my hacky solution to the problem of single preformatted
     // lines (if there arent any multi line code blocks in the rest of the
-    // document) not rendering properly until the window is resized
-    widget.set_height_request(20);
+    // document) not rendering properly until the window is resized.
+    // The class sets a minimum height in em so it scales with the zoom level
+    widget.add_css_class("code-block");
     widget
 }
 
-fn calculate_indentation_value(level: line_types::Level) -> i32 {
-    // TODO: It's probably not a good idea to use a fixed number like this for indentation
+/// The indentation is set in em by the indent-<level> classes in style.css, so it scales with the
+/// zoom level and font size
+fn indentation_class(level: line_types::Level) -> &'static str {
     use line_types::Level::*;
-    let level = match level {
-        One => 1,
-        Two => 2,
-        Three => 3,
-        Four => 4,
-        Five => 5,
-        Six => 6,
-    };
-    level * 12
+    match level {
+        One => "indent-1",
+        Two => "indent-2",
+        Three => "indent-3",
+        Four => "indent-4",
+        Five => "indent-5",
+        Six => "indent-6",
+    }
 }
 
 fn create_ulist_line(level: line_types::Level, content: String) -> Label {
-    Label::builder()
+    let widget = Label::builder()
         .label(format!("• {}", content))
         .halign(gtk::Align::Start)
         .wrap(true)
         .wrap_mode(gtk::pango::WrapMode::WordChar)
-        .margin_start(calculate_indentation_value(level))
-        .build()
-}
-
-fn calculate_indentation_value_with_bullet(level: line_types::Level, bullet: String) -> i32 {
-    let bullet_width = 5 * bullet.len() as i32; // TODO: Crude approximation, could definitely be better
-    std::cmp::max(calculate_indentation_value(level) - bullet_width, 0)
+        .build();
+    widget.add_css_class(indentation_class(level));
+    widget
 }
 
 fn create_olist_line(level: line_types::Level, bullet: String, content: String) -> Label {
-    Label::builder()
+    let widget = Label::builder()
         .label(format!("{} {}", bullet, content))
         .halign(gtk::Align::Start)
         .wrap(true)
         .wrap_mode(gtk::pango::WrapMode::WordChar)
-        .margin_start(calculate_indentation_value_with_bullet(level, bullet))
-        .build()
+        .build();
+    widget.add_css_class(indentation_class(level));
+    widget
 }
 
 fn create_dropdown_line(label: String, content: String) -> ListBox {
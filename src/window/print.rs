@@ -0,0 +1,376 @@
+//! Lays documents out for printing. The canvas is made of widgets that only make sense on a
+//! screen, so the document is turned into a list of styled blocks of pango markup instead
+use crate::athn_document::line_types::{AdmonitionType, FooterLine, Level, Link, MainLine};
+use crate::athn_document::Document;
+use url::Url;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlockStyle {
+    Title,
+    Metaline,
+    Subtitle,
+    Heading(u8),
+    Text,
+    Link,
+    Preformatted,
+    /// The list level, starting at 1
+    ListItem(u8),
+    DropdownLabel,
+    DropdownContent,
+    Admonition,
+    Quote,
+    /// Drawn as a horizontal line, the block has no text
+    Separator,
+    Footer,
+}
+
+impl BlockStyle {
+    /// Space above the block in em, it's left out at the top of a page
+    pub fn spacing(&self) -> f64 {
+        use BlockStyle::*;
+        match self {
+            Title | Metaline | Subtitle | DropdownContent => 0.2,
+            Heading(_) => 1.0,
+            _ => 0.6,
+        }
+    }
+
+    /// Indentation of the block in em
+    pub fn indent(&self) -> f64 {
+        match self {
+            BlockStyle::ListItem(level) => 0.8 * *level as f64,
+            BlockStyle::DropdownContent => 1.2,
+            _ => 0.0,
+        }
+    }
+
+    fn wrap(&self, markup: &str) -> String {
+        use BlockStyle::*;
+        let attributes = match self {
+            Title => "size=\"xx-large\" weight=\"bold\"",
+            Metaline | Footer => "size=\"small\"",
+            Subtitle => "size=\"large\"",
+            Heading(1) => "size=\"x-large\" weight=\"bold\"",
+            Heading(2) => "size=\"large\" weight=\"bold\"",
+            Heading(3 | 4) => "weight=\"bold\"",
+            Heading(_) => "size=\"small\" weight=\"bold\"",
+            Preformatted => "font_family=\"monospace\"",
+            DropdownLabel => "weight=\"bold\"",
+            Quote => "style=\"italic\"",
+            Text | Link | ListItem(_) | DropdownContent | Admonition | Separator => {
+                return markup.to_string()
+            }
+        };
+        format!("<span {attributes}>{markup}</span>")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrintBlock {
+    pub style: BlockStyle,
+    /// The content as pango markup, without the styling of the block
+    pub content: String,
+}
+
+impl PrintBlock {
+    fn new(style: BlockStyle, content: String) -> Self {
+        PrintBlock { style, content }
+    }
+
+    /// The content with the styling of the block applied
+    pub fn markup(&self) -> String {
+        self.style.wrap(&self.content)
+    }
+}
+
+/// Turns a document into the blocks that get printed. Dropdowns are printed expanded and form
+/// fields are left out, they can't be filled in on paper
+pub fn print_blocks(document: &Document, base_url: &Url) -> Vec<PrintBlock> {
+    use BlockStyle::*;
+    let metadata = &document.metadata;
+    let mut blocks = vec![PrintBlock::new(Title, escape(&metadata.title))];
+    if let Some(metaline) = metaline_text(metadata.author.as_deref(), metadata.license.as_deref()) {
+        blocks.push(PrintBlock::new(Metaline, escape(&metaline)));
+    }
+    if let Some(subtitle) = &metadata.subtitle {
+        blocks.push(PrintBlock::new(Subtitle, escape(subtitle)));
+    }
+    blocks.push(PrintBlock::new(Separator, String::new()));
+
+    for line in &document.main {
+        let block = match line {
+            MainLine::TextLine(content) => PrintBlock::new(Text, formatting_markup(content)),
+            MainLine::LinkLine(link) => PrintBlock::new(Link, link_markup(link, base_url)),
+            MainLine::PreformattedLine(_, content) => {
+                PrintBlock::new(Preformatted, escape(content))
+            }
+            MainLine::SeparatorLine => PrintBlock::new(Separator, String::new()),
+            MainLine::UListLine(level, content) => PrintBlock::new(
+                ListItem(level_number(level)),
+                format!("• {}", escape(content)),
+            ),
+            MainLine::OListLine(level, bullet, content) => PrintBlock::new(
+                ListItem(level_number(level)),
+                format!("{} {}", escape(bullet), escape(content)),
+            ),
+            MainLine::DropdownLine(label, content) => {
+                blocks.push(PrintBlock::new(DropdownLabel, escape(label)));
+                PrintBlock::new(DropdownContent, escape(content))
+            }
+            MainLine::AdmonitionLine(type_, content) => PrintBlock::new(
+                Admonition,
+                format!("<b>{}:</b> {}", admonition_name(type_), escape(content)),
+            ),
+            MainLine::HeadingLine(level, content) => {
+                PrintBlock::new(Heading(level_number(level)), escape(content))
+            }
+            MainLine::QuoteLine(content) => {
+                PrintBlock::new(Quote, format!("“{}”", escape(content)))
+            }
+            MainLine::FormFieldLine(..) => continue,
+        };
+        push_block(&mut blocks, block);
+    }
+
+    if let Some(footer) = &document.footer {
+        blocks.push(PrintBlock::new(Separator, String::new()));
+        for line in footer {
+            let content = match line {
+                FooterLine::LinkLine(link) => link_markup(link, base_url),
+                FooterLine::TextLine(content) => escape(content),
+            };
+            blocks.push(PrintBlock::new(Footer, content));
+        }
+    }
+    blocks
+}
+
+/// The blocks as plain text for a terminal, indentation is done with spaces and separators are
+/// drawn with dashes
+pub fn plain_text(blocks: &[PrintBlock]) -> String {
+    blocks
+        .iter()
+        .map(|block| {
+            if block.style == BlockStyle::Separator {
+                return "-".repeat(40);
+            }
+            // Two and a half characters are about as wide as an em
+            let indent = " ".repeat((block.style.indent() * 2.5).round() as usize);
+            strip_markup(&block.content)
+                .lines()
+                .map(|line| format!("{indent}{line}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Prints the source of a document as it is shown in the view source mode
+pub fn source_blocks(source: &str) -> Vec<PrintBlock> {
+    vec![PrintBlock::new(BlockStyle::Preformatted, escape(source))]
+}
+
+/// Consecutive text and preformatted lines are shown as one block on the canvas, so they're
+/// printed as one too
+fn push_block(blocks: &mut Vec<PrintBlock>, block: PrintBlock) {
+    match blocks.last_mut() {
+        Some(last)
+            if last.style == block.style
+                && matches!(block.style, BlockStyle::Text | BlockStyle::Preformatted) =>
+        {
+            last.content.push('\n');
+            last.content.push_str(&block.content);
+        }
+        _ => blocks.push(block),
+    }
+}
+
+fn level_number(level: &Level) -> u8 {
+    use Level::*;
+    match level {
+        One => 1,
+        Two => 2,
+        Three => 3,
+        Four => 4,
+        Five => 5,
+        Six => 6,
+    }
+}
+
+fn admonition_name(type_: &AdmonitionType) -> &'static str {
+    match type_ {
+        AdmonitionType::Note => "Note",
+        AdmonitionType::Warning => "Warning",
+        AdmonitionType::Danger => "Danger",
+    }
+}
+
+/// Paper can't be clicked, so the URL is printed after the label
+fn link_markup(link: &Link, base_url: &Url) -> String {
+    let url = base_url
+        .join(&link.url)
+        .map(String::from)
+        .unwrap_or(link.url.clone());
+    match &link.label {
+        Some(label) => format!("{} ({})", escape(label), escape(&url)),
+        None => escape(&url),
+    }
+}
+
+/// The line under the title with the authors and licenses, it's shared with the canvas
+pub fn metaline_text(author: Option<&[String]>, license: Option<&[String]>) -> Option<String> {
+    let author = author.map(|author| format!("By: {}", author.join(", ")));
+    let license = license.map(|license| {
+        format!(
+            "License{}: {}",
+            if license.len() > 1 { "s" } else { "" },
+            license.join(", ")
+        )
+    });
+    match (author, license) {
+        (None, None) => None,
+        (Some(author), Some(license)) => Some(format!("{author}. {license}")),
+        (author, license) => author.or(license),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Removes the tags from markup made by this module and unescapes the text
+fn strip_markup(markup: &str) -> String {
+    let mut text = String::new();
+    let mut rest = markup;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        rest = rest[start..].split_once('>').map_or("", |(_, rest)| rest);
+    }
+    text.push_str(rest);
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// Turns the \b, \i and \p formatting markers of a text line into pango markup, \r ends all of
+/// them
+fn formatting_markup(text: &str) -> String {
+    let mut markup = String::new();
+    let mut open: Vec<&str> = vec![];
+    let mut rest = text;
+    while let Some(i) = rest.find('\\') {
+        markup.push_str(&escape(&rest[..i]));
+        let tag = match rest[i + 1..].chars().next() {
+            Some('b') => "b",
+            Some('i') => "i",
+            Some('p') => "tt",
+            Some('r') => "",
+            _ => {
+                markup.push('\\');
+                rest = &rest[i + 1..];
+                continue;
+            }
+        };
+        match tag {
+            "" => close_tags(&mut markup, &mut open),
+            tag if !open.contains(&tag) => {
+                markup.push_str(&format!("<{tag}>"));
+                open.push(tag);
+            }
+            _ => (),
+        }
+        rest = &rest[i + 2..];
+    }
+    markup.push_str(&escape(rest));
+    close_tags(&mut markup, &mut open);
+    markup
+}
+
+fn close_tags(markup: &mut String, open: &mut Vec<&str>) {
+    for tag in open.drain(..).rev() {
+        markup.push_str(&format!("</{tag}>"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::athn_document::{self, ParserState};
+
+    fn blocks(source: &str) -> Vec<PrintBlock> {
+        let document =
+            athn_document::parse(source.lines(), Document::builder(), ParserState::default())
+                .unwrap()
+                .build();
+        print_blocks(&document, &Url::parse("https://athn.online/dir/").unwrap())
+    }
+
+    #[test]
+    fn metaline() {
+        let authors = ["A".to_string(), "B".to_string()];
+        let licenses = ["CC0".to_string()];
+
+        assert_eq!(
+            metaline_text(Some(&authors), Some(&licenses)),
+            Some("By: A, B. License: CC0".to_string())
+        );
+        assert_eq!(metaline_text(None, None), None);
+    }
+
+    #[test]
+    fn formatting_markers() {
+        assert_eq!(
+            formatting_markup("a \\bbold \\iand italic\\r <done>"),
+            "a <b>bold <i>and italic</i></b> &lt;done&gt;"
+        );
+        assert_eq!(formatting_markup("\\punclosed"), "<tt>unclosed</tt>");
+    }
+
+    #[test]
+    fn consecutive_lines_are_merged() {
+        let blocks = blocks("TM Title\n+++\nFirst\nSecond\n1# Heading\nThird");
+
+        assert_eq!(
+            &blocks[2..],
+            [
+                PrintBlock::new(BlockStyle::Text, "First\nSecond".to_string()),
+                PrintBlock::new(BlockStyle::Heading(1), "Heading".to_string()),
+                PrintBlock::new(BlockStyle::Text, "Third".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn links_show_absolute_url() {
+        let blocks = blocks("TM Title\n+++\n@@@page.athn | Page");
+
+        assert_eq!(
+            blocks.last().unwrap().content,
+            "Page (https://athn.online/dir/page.athn)"
+        );
+    }
+
+    #[test]
+    fn plain_text_without_markup() {
+        let blocks = blocks("TM A & B\n+++\n1- \\bBold\\r <item>\n2- Nested");
+
+        assert_eq!(
+            plain_text(&blocks),
+            "A & B\n\n----------------------------------------\n\n  • \\bBold\\r <item>\n\n    • Nested"
+        );
+    }
+
+    #[test]
+    fn dropdown_is_expanded() {
+        let blocks = blocks("TM Title\n+++\n...Label | Content");
+
+        assert_eq!(blocks[2].style, BlockStyle::DropdownLabel);
+        assert_eq!(
+            blocks[3],
+            PrintBlock::new(BlockStyle::DropdownContent, "Content".to_string())
+        );
+    }
+}
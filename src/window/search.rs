@@ -0,0 +1,195 @@
+//! Tells URLs and search queries typed into the address bar apart
+use url::form_urlencoded;
+
+/// Schemes that are always treated as a URL, even if the rest of the input looks like a query
+const URL_PREFIXES: [&str; 4] = ["https://", "file://", "about:", "view-source:"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchProvider {
+    pub name: String,
+    /// Typing the keyword followed by a query searches with this provider
+    pub keyword: String,
+    /// The URL of the search results, %s is replaced with the query
+    pub template: String,
+}
+
+impl SearchProvider {
+    pub fn search_url(&self, query: &str) -> String {
+        let query: String = form_urlencoded::byte_serialize(query.as_bytes()).collect();
+        self.template.replace("%s", &query)
+    }
+}
+
+impl From<(String, String, String)> for SearchProvider {
+    fn from((name, keyword, template): (String, String, String)) -> Self {
+        SearchProvider {
+            name,
+            keyword,
+            template,
+        }
+    }
+}
+
+impl From<&SearchProvider> for (String, String, String) {
+    fn from(provider: &SearchProvider) -> Self {
+        (
+            provider.name.clone(),
+            provider.keyword.clone(),
+            provider.template.clone(),
+        )
+    }
+}
+
+/// Returns the URL to go to for the text in the address bar. Searches with the default provider
+/// unless the first word is the keyword of another one
+pub fn resolve_input(
+    input: &str,
+    providers: &[SearchProvider],
+    default_keyword: &str,
+) -> Result<String, &'static str> {
+    let input = input.trim();
+    if input.is_empty() || looks_like_url(input) {
+        return Ok(input.to_string());
+    }
+
+    if let Some((keyword, query)) = input.split_once(char::is_whitespace) {
+        if let Some(provider) = providers.iter().find(|p| p.keyword == keyword) {
+            return Ok(provider.search_url(query.trim()));
+        }
+    }
+
+    let provider = providers
+        .iter()
+        .find(|p| p.keyword == default_keyword)
+        .or(providers.first())
+        .ok_or(
+            "There are no search providers, add one in the settings to search from the address bar",
+        )?;
+    Ok(provider.search_url(input))
+}
+
+fn looks_like_url(input: &str) -> bool {
+    if URL_PREFIXES.iter().any(|prefix| input.starts_with(prefix)) {
+        return true;
+    }
+    if input.contains(char::is_whitespace) {
+        return false;
+    }
+    // A host needs a dot or a port to be told apart from a single search word
+    let host = input.split('/').next().unwrap_or_default();
+    host.contains('.') || host.contains(':') || host == "localhost"
+}
+
+/// Checks a provider before it's added, returns an error message if it's unusable
+pub fn validate_provider(provider: &SearchProvider) -> Result<(), &'static str> {
+    if provider.name.trim().is_empty() {
+        return Err("The search provider needs a name");
+    }
+    if provider.keyword.is_empty() || provider.keyword.contains(char::is_whitespace) {
+        return Err("The keyword has to be a single word");
+    }
+    if !provider.template.contains("%s") {
+        return Err("The URL template has to contain %s where the query goes");
+    }
+    if url::Url::parse(&provider.search_url("test")).is_err() {
+        return Err("The URL template is not a valid absolute URL");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn providers() -> Vec<SearchProvider> {
+        vec![
+            SearchProvider {
+                name: "First".to_string(),
+                keyword: "f".to_string(),
+                template: "https://first.example/search.athn?q=%s".to_string(),
+            },
+            SearchProvider {
+                name: "Second".to_string(),
+                keyword: "s".to_string(),
+                template: "https://second.example/%s".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn url_is_not_searched() {
+        let resolved = resolve_input("athn.online/index.athn", &providers(), "f");
+
+        assert_eq!(resolved, Ok("athn.online/index.athn".to_string()));
+    }
+
+    #[test]
+    fn view_source_is_url() {
+        let resolved = resolve_input("view-source:athn.online", &providers(), "f");
+
+        assert_eq!(resolved, Ok("view-source:athn.online".to_string()));
+    }
+
+    #[test]
+    fn localhost_with_port_is_url() {
+        let resolved = resolve_input("localhost:8080", &providers(), "f");
+
+        assert_eq!(resolved, Ok("localhost:8080".to_string()));
+    }
+
+    #[test]
+    fn words_are_searched_with_default() {
+        let resolved = resolve_input("some words", &providers(), "s");
+
+        assert_eq!(
+            resolved,
+            Ok("https://second.example/some+words".to_string())
+        );
+    }
+
+    #[test]
+    fn single_word_is_searched() {
+        let resolved = resolve_input("athn", &providers(), "f");
+
+        assert_eq!(
+            resolved,
+            Ok("https://first.example/search.athn?q=athn".to_string())
+        );
+    }
+
+    #[test]
+    fn keyword_shortcut() {
+        let resolved = resolve_input("s query & more", &providers(), "f");
+
+        assert_eq!(
+            resolved,
+            Ok("https://second.example/query+%26+more".to_string())
+        );
+    }
+
+    #[test]
+    fn unknown_default_falls_back_to_first() {
+        let resolved = resolve_input("query", &providers(), "nonexistent");
+
+        assert_eq!(
+            resolved,
+            Ok("https://first.example/search.athn?q=query".to_string())
+        );
+    }
+
+    #[test]
+    fn search_without_providers() {
+        assert!(resolve_input("query", &[], "f").is_err());
+    }
+
+    #[test]
+    fn template_without_placeholder() {
+        let provider = SearchProvider {
+            name: "Broken".to_string(),
+            keyword: "b".to_string(),
+            template: "https://broken.example/".to_string(),
+        };
+
+        assert!(validate_provider(&provider).is_err());
+    }
+}
@@ -0,0 +1,200 @@
+//! Syntax highlighting for the view source mode
+use crate::athn_document::form::FormField;
+use crate::athn_document::line_types::MainLine;
+use crate::athn_document::{self, Document, ParserState};
+
+/// URLs with this prefix show the source of the document instead of rendering it
+pub const VIEW_SOURCE_PREFIX: &str = "view-source:";
+
+const METADATA_TAGS: [&str; 6] = ["TM ", "SM ", "AM ", "RM ", "LM ", "CM "];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineKind {
+    Section,
+    Metadata,
+    Heading,
+    Link,
+    Preformatted,
+    List,
+    Admonition,
+    Dropdown,
+    Quote,
+    Separator,
+    FormField,
+    Text,
+    /// The line can't be parsed
+    Invalid,
+}
+
+impl LineKind {
+    /// The name of the text tag used to highlight this kind of line, None for plain text
+    pub fn tag_name(&self) -> Option<&'static str> {
+        use LineKind::*;
+        match self {
+            Section => Some("source-section"),
+            Metadata => Some("source-metadata"),
+            Heading => Some("source-heading"),
+            Link => Some("source-link"),
+            Preformatted => Some("source-preformatted"),
+            List | Separator => Some("source-list"),
+            Admonition => Some("source-admonition"),
+            Dropdown | Quote => Some("source-quote"),
+            FormField => Some("source-form"),
+            Text => None,
+            Invalid => Some("source-invalid"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Section {
+    Meta,
+    Main,
+    Form,
+    Header,
+    Footer,
+}
+
+/// Figures out the kind of every line, keeping track of the current section the same way the
+/// parser does
+pub fn classify_lines(source: &str) -> Vec<LineKind> {
+    let mut section = Section::Meta;
+    source
+        .lines()
+        .map(|line| {
+            if line.starts_with("+++") {
+                section = match line {
+                    "+++ Header" => Section::Header,
+                    "+++ Footer" => Section::Footer,
+                    "+++ Form" => Section::Form,
+                    _ => Section::Main,
+                };
+                return LineKind::Section;
+            }
+            if line.is_empty() {
+                return LineKind::Text;
+            }
+            match section {
+                Section::Meta if METADATA_TAGS.iter().any(|tag| line.starts_with(tag)) => {
+                    LineKind::Metadata
+                }
+                Section::Meta => LineKind::Invalid,
+                Section::Form => match line.split_once("???") {
+                    Some((_, field)) if FormField::parse(field).is_ok() => LineKind::FormField,
+                    Some(_) => LineKind::Invalid,
+                    None => classify_main_line(line),
+                },
+                Section::Main => classify_main_line(line),
+                Section::Header if line.contains("@@@") => LineKind::Link,
+                Section::Header => LineKind::Invalid,
+                Section::Footer if line.contains("@@@") => LineKind::Link,
+                Section::Footer => LineKind::Text,
+            }
+        })
+        .collect()
+}
+
+fn classify_main_line(line: &str) -> LineKind {
+    use MainLine::*;
+    match MainLine::parse(line) {
+        Err(_) => LineKind::Invalid,
+        Ok(TextLine(_)) => LineKind::Text,
+        Ok(LinkLine(_)) => LineKind::Link,
+        Ok(PreformattedLine(..)) => LineKind::Preformatted,
+        Ok(SeparatorLine) => LineKind::Separator,
+        Ok(UListLine(..) | OListLine(..)) => LineKind::List,
+        Ok(DropdownLine(..)) => LineKind::Dropdown,
+        Ok(AdmonitionLine(..)) => LineKind::Admonition,
+        Ok(HeadingLine(..)) => LineKind::Heading,
+        Ok(QuoteLine(_)) => LineKind::Quote,
+        Ok(FormFieldLine(..)) => LineKind::FormField,
+    }
+}
+
+/// Returns the index of the line the parser fails on, or None if the document is valid
+pub fn error_line(source: &str) -> Option<usize> {
+    // Byte offset of the end of every line, so a prefix of the document can be sliced out
+    let line_ends: Vec<usize> = source
+        .split_inclusive('\n')
+        .scan(0, |end, line| {
+            *end += line.len();
+            Some(*end)
+        })
+        .collect();
+    let fails = |line_count: usize| {
+        let prefix = match line_count {
+            0 => "",
+            n => &source[..line_ends[n - 1]],
+        };
+        athn_document::parse(prefix.lines(), Document::builder(), ParserState::default()).is_err()
+    };
+
+    if !fails(line_ends.len()) {
+        return None;
+    }
+    // The parser stops at the first invalid line, so every part of the document that includes
+    // that line fails to parse and it can be found with a binary search
+    let (mut valid, mut invalid) = (0, line_ends.len());
+    while invalid - valid > 1 {
+        let middle = (valid + invalid) / 2;
+        if fails(middle) {
+            invalid = middle;
+        } else {
+            valid = middle;
+        }
+    }
+    Some(invalid - 1)
+}
+
+/// The numbers shown next to the source, one per line
+pub fn line_numbers(line_count: usize) -> String {
+    (1..=line_count.max(1))
+        .map(|number| number.to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_sections() {
+        let source = "TM Title\n+++\n1# Heading\n@@@https://athn.online\n+++ Header\nnot a link";
+
+        assert_eq!(
+            classify_lines(source),
+            vec![
+                LineKind::Metadata,
+                LineKind::Section,
+                LineKind::Heading,
+                LineKind::Link,
+                LineKind::Section,
+                LineKind::Invalid,
+            ]
+        );
+    }
+
+    #[test]
+    fn valid_document_has_no_error() {
+        assert_eq!(error_line("TM Title\n+++\nText\n"), None);
+    }
+
+    #[test]
+    fn finds_error_line() {
+        let source =
+            "TM Title\n+++\nText\nMore text\n+++ Header\nnot a link\n@@@https://athn.online";
+
+        assert_eq!(error_line(source), Some(5));
+    }
+
+    #[test]
+    fn error_on_first_line() {
+        assert_eq!(error_line("Not metadata\n+++\n"), Some(0));
+    }
+
+    #[test]
+    fn numbers_for_every_line() {
+        assert_eq!(line_numbers(3), "1\n2\n3");
+    }
+}
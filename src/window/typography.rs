@@ -0,0 +1,89 @@
+//! Page zoom and the fonts used on the canvas
+use super::source::VIEW_SOURCE_PREFIX;
+use url::Url;
+
+/// Page zoom in percent, every level has a matching zoom-<level> class in style.css
+pub const ZOOM_LEVELS: [u32; 13] = [50, 67, 80, 90, 100, 110, 120, 133, 150, 170, 200, 240, 300];
+pub const DEFAULT_ZOOM: u32 = 100;
+
+/// Returns the index of the zoom level closest to the percentage
+pub fn zoom_level(zoom: u32) -> usize {
+    ZOOM_LEVELS
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, level)| level.abs_diff(zoom))
+        .map(|(i, _)| i)
+        .unwrap_or_default()
+}
+
+/// Pages on the same site share their zoom level. Internal pages dont belong to a site so they
+/// return None
+pub fn zoom_site(uri: &str) -> Option<String> {
+    let uri = uri.strip_prefix(VIEW_SOURCE_PREFIX).unwrap_or(uri);
+    let url = Url::parse(uri).ok()?;
+    match url.scheme() {
+        "https" => url.host_str().map(|host| host.to_string()),
+        // All local documents are treated as one site
+        "file" => Some("file://".to_string()),
+        _ => None,
+    }
+}
+
+/// Generates the style sheet that applies the font settings to the canvas. An empty font family
+/// keeps the default font
+pub fn typography_css(body_font: &str, monospace_font: &str) -> String {
+    // Quotes would end the family name early
+    let family = |font: &str| font.replace(['"', '\\'], "");
+    let mut css = String::new();
+    if !body_font.trim().is_empty() {
+        css.push_str(&format!(
+            ".canvas {{ font-family: \"{}\"; }}\n",
+            family(body_font.trim())
+        ));
+    }
+    if !monospace_font.trim().is_empty() {
+        css.push_str(&format!(
+            ".canvas .monospace {{ font-family: \"{}\"; }}\n",
+            family(monospace_font.trim())
+        ));
+    }
+    css
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closest_zoom_level() {
+        assert_eq!(ZOOM_LEVELS[zoom_level(100)], 100);
+        assert_eq!(ZOOM_LEVELS[zoom_level(125)], 120);
+        assert_eq!(ZOOM_LEVELS[zoom_level(1000)], 300);
+    }
+
+    #[test]
+    fn source_shares_zoom_with_page() {
+        assert_eq!(
+            zoom_site("view-source:https://athn.online/index.athn"),
+            zoom_site("https://athn.online/other.athn")
+        );
+    }
+
+    #[test]
+    fn internal_pages_have_no_site() {
+        assert_eq!(zoom_site("about:history"), None);
+    }
+
+    #[test]
+    fn default_fonts_have_no_css() {
+        assert_eq!(typography_css("", " "), "");
+    }
+
+    #[test]
+    fn font_family_is_quoted() {
+        assert_eq!(
+            typography_css("Noto \"Serif\"", ""),
+            ".canvas { font-family: \"Noto Serif\"; }\n"
+        );
+    }
+}